@@ -0,0 +1,393 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasherDefault, Hasher};
+use std::rc::Rc;
+
+use crate::captures::Captures;
+use crate::haystack::Haystack;
+use crate::matches::Match;
+use crate::nfa::{check_assertion, Inst, Program};
+
+/// Entry of the backtracking stack
+enum Frame {
+    /// Alternative to try once the current one fails
+    Step { pc: usize, position: usize },
+    /// Value of the slot before an instruction changed it
+    RestoreSlot { slot: usize, value: Option<usize> },
+    /// Position the split was followed at before the current path followed it
+    RestoreFollowed { pc: usize, position: Option<usize> },
+    /// State of a subprogram on the current path, which the subprogram matches from if
+    /// the path reaches its `Match`
+    OnPath(State),
+}
+
+/// Instruction, position and the position the (sub)program match has to end at,
+/// `usize::MAX` if it may end anywhere
+type State = (usize, usize, usize);
+
+type BuildPositionHasher = BuildHasherDefault<PositionHasher>;
+
+/// Hasher of states, several times faster than the default one which resists
+/// collisions crafted on purpose
+#[derive(Default)]
+struct PositionHasher(u64);
+
+impl Hasher for PositionHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        bytes
+            .iter()
+            .for_each(|&byte| self.write_usize(usize::from(byte)));
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.0 = (self.0.rotate_left(5) ^ value as u64).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+}
+
+/// Outcomes of the states tried so far. Without backreferences they depend only on the
+/// state, so they hold for every later attempt, including the ones of the subprograms
+/// of atomic groups, possessive quantifiers and lookarounds at other positions.
+#[derive(Default)]
+struct Memo {
+    /// States which failed, or which are on the current path so trying them again goes
+    /// round a loop without consuming input
+    tried: HashSet<State, BuildPositionHasher>,
+    /// States of subprograms first at their position on the path of a match. Later states
+    /// at the position may match differently when run again, as the path cut off their
+    /// loops round to the earlier ones.
+    matched: HashMap<State, SubMatch, BuildPositionHasher>,
+    /// States of the subprograms running which failed. A state may fail only because
+    /// it goes round to a state on the path, so its failure is forgotten once the
+    /// subprogram matches.
+    failed: Vec<State>,
+}
+
+/// First match of a subprogram from a state on its path
+struct SubMatch {
+    end: usize,
+    /// Last value the path wrote into every slot it changed, ordered by the number of
+    /// writes on the path before that last one
+    writes: Rc<[(usize, usize, Option<usize>)]>,
+    /// Number of writes on the path before the state
+    after: usize,
+}
+
+/// Matcher trying every way the program can match, in the order of preference: the
+/// first target of every split before the second one. Alternatives left to try are kept
+/// on an explicit stack, so long inputs do not exhaust the call stack.
+///
+/// Without backreferences, a state which failed is not tried again and a subprogram run
+/// into the state it matched from before ends right away, so the time is polynomial in
+/// the input length.
+pub(crate) struct Backtracker<'p, 'h> {
+    program: &'p Program,
+    haystack: Haystack<'h>,
+    group_count: usize,
+    slots: Vec<Option<usize>>,
+    stack: Vec<Frame>,
+    /// `None` with backreferences, as whether the program matches from a state then
+    /// depends on the captured text too
+    memo: Option<Memo>,
+    /// Position every split was last followed at on the current path, without the memo.
+    /// Following a split again at the same position goes round a loop without consuming
+    /// input, so the path is cut off there, like a thread reaching an instruction the
+    /// Pike VM already has at the position.
+    followed: Vec<Option<usize>>,
+}
+
+impl<'p, 'h> Backtracker<'p, 'h> {
+    pub fn new(program: &'p Program, haystack: Haystack<'h>, group_count: usize) -> Self {
+        Self {
+            program,
+            haystack,
+            group_count,
+            slots: vec![None; program.slot_count],
+            stack: Vec::new(),
+            memo: (!program.has_backreferences).then(Memo::default),
+            followed: vec![None; program.insts.len()],
+        }
+    }
+
+    /// Finds the leftmost match starting at or after the `start` byte offset
+    pub fn captures_at(&mut self, start: usize) -> Option<Captures> {
+        let mut position = start;
+        loop {
+            self.slots.fill(None);
+            if self.run(0, position, None, false).is_some() {
+                let mut captures = Captures::new(self.group_count);
+                for (group, span) in self.slots.chunks(2).take(self.group_count + 1).enumerate() {
+                    if let [Some(start), Some(end)] = span {
+                        captures.set(group, Some(Match::new(*start, *end)));
+                    }
+                }
+                return Some(captures);
+            }
+            if self.program.is_start_anchored {
+                return None;
+            }
+            let (_, len) = self.haystack.char_at(position)?;
+            position += len;
+        }
+    }
+
+    /// Runs the (sub)program starting at `pc` until it reaches its `Match`, at the
+    /// `end` position if one is required. Returns the end of the first such match, with
+    /// the changes to the slots left on the stack for backtracking over the subprogram.
+    fn run(
+        &mut self,
+        pc: usize,
+        position: usize,
+        end: Option<usize>,
+        is_subprogram: bool,
+    ) -> Option<usize> {
+        let base = self.stack.len();
+        let failed = self.memo.as_ref().map_or(0, |memo| memo.failed.len());
+        self.stack.push(Frame::Step { pc, position });
+        // Alternatives continue the path at the position of their split
+        let mut is_entry = true;
+        while self.stack.len() > base {
+            match self.stack.pop()? {
+                Frame::Step { pc, position } => {
+                    let is_first = std::mem::take(&mut is_entry);
+                    if let Some(matched) = self.step(pc, position, end, is_subprogram, is_first) {
+                        self.cut(base, failed, matched);
+                        return Some(matched);
+                    }
+                }
+                Frame::RestoreSlot { slot, value } => self.slots[slot] = value,
+                Frame::RestoreFollowed { pc, position } => self.followed[pc] = position,
+                Frame::OnPath(state) => {
+                    if let Some(memo) = &mut self.memo {
+                        memo.failed.push(state);
+                    }
+                }
+            }
+        }
+        // The subprogram fails from every state it tried, whatever the path it runs on
+        if let Some(memo) = &mut self.memo {
+            memo.failed.truncate(failed);
+        }
+        None
+    }
+
+    /// Cuts off the alternatives of a subprogram which matched up to `end`, keeping its
+    /// changes to the slots. Its splits are no longer on the current path, the states on
+    /// the path first at their position are remembered as matching and the states on the
+    /// path or failed are tried again next time.
+    fn cut(&mut self, base: usize, failed: usize, end: usize) {
+        let frames = self.stack.split_off(base);
+        for frame in frames.iter().rev() {
+            if let Frame::RestoreFollowed { pc, position } = frame {
+                self.followed[*pc] = *position;
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut last_writes = vec![None; self.slots.len()];
+        let mut writes = 0;
+        for frame in frames {
+            match frame {
+                Frame::RestoreSlot { slot, .. } => {
+                    last_writes[slot] = Some(writes);
+                    writes += 1;
+                    self.stack.push(frame);
+                }
+                Frame::OnPath(state) => path.push((state, writes)),
+                Frame::Step { .. } | Frame::RestoreFollowed { .. } => {}
+            }
+        }
+        let Some(memo) = &mut self.memo else {
+            return;
+        };
+        let path_states = path.iter().map(|(state, _)| *state);
+        for state in memo.failed.drain(failed..).chain(path_states) {
+            memo.tried.remove(&state);
+        }
+        if path.is_empty() {
+            return;
+        }
+        let mut last_writes = last_writes
+            .into_iter()
+            .enumerate()
+            .filter_map(|(slot, index)| Some((index?, slot, self.slots[slot])))
+            .collect::<Vec<_>>();
+        last_writes.sort_unstable();
+        let last_writes = Rc::<[_]>::from(last_writes);
+        let mut previous = None;
+        for (state @ (_, position, _), after) in path {
+            if previous.replace(position) != Some(position) {
+                let writes = last_writes.clone();
+                memo.matched.insert(state, SubMatch { end, writes, after });
+            }
+        }
+    }
+
+    /// Follows the instructions from `pc` until they fail or match, pushing the
+    /// alternatives of every split on the way. A subprogram matches from a state the way
+    /// it matched before only if the state is the first one at its position on the path,
+    /// as `is_first` tells for the one at `pc`.
+    fn step(
+        &mut self,
+        mut pc: usize,
+        mut position: usize,
+        end: Option<usize>,
+        is_subprogram: bool,
+        mut is_first: bool,
+    ) -> Option<usize> {
+        loop {
+            if let Some(memo) = &mut self.memo {
+                let state = (pc, position, end.unwrap_or(usize::MAX));
+                if let Some(matched) = memo.matched.get(&state).filter(|_| is_first) {
+                    let (end, writes, after) = (matched.end, matched.writes.clone(), matched.after);
+                    for &(_, slot, value) in writes.iter().filter(|(index, ..)| *index >= after) {
+                        self.save(slot, value);
+                    }
+                    return Some(end);
+                }
+                if !memo.tried.insert(state) {
+                    return None;
+                }
+                if is_subprogram {
+                    self.stack.push(Frame::OnPath(state));
+                }
+            }
+            let previous = position;
+            match &self.program.insts[pc] {
+                Inst::Match => return end.is_none_or(|end| end == position).then_some(position),
+                Inst::Char(token) => {
+                    let (ch, len) = self.haystack.char_at(position)?;
+                    if !token.match_char(ch) {
+                        return None;
+                    }
+                    position += len;
+                    pc += 1;
+                }
+                Inst::NotChar(token) => {
+                    if let Some((ch, _)) = self.haystack.char_at(position) {
+                        if token.match_char(ch) {
+                            return None;
+                        }
+                    }
+                    pc += 1;
+                }
+                Inst::Assert(token) => {
+                    if !check_assertion(token, self.haystack, position) {
+                        return None;
+                    }
+                    pc += 1;
+                }
+                Inst::Split(first, second) => {
+                    if self.memo.is_none() {
+                        if self.followed[pc] == Some(position) {
+                            return None;
+                        }
+                        let position = self.followed[pc].replace(position);
+                        self.stack.push(Frame::RestoreFollowed { pc, position });
+                    }
+                    self.stack.push(Frame::Step {
+                        pc: *second,
+                        position,
+                    });
+                    pc = *first;
+                }
+                Inst::Jump(to) => pc = *to,
+                Inst::Save(slot) => {
+                    self.save(*slot, Some(position));
+                    pc += 1;
+                }
+                Inst::SaveGroup { group, start } => {
+                    self.save(group * 2, self.slots[*start]);
+                    self.save(group * 2 + 1, Some(position));
+                    pc += 1;
+                }
+                Inst::Backreference {
+                    group,
+                    case_insensitive,
+                } => {
+                    position = self.match_backreference(*group, *case_insensitive, position)?;
+                    pc += 1;
+                }
+                Inst::Atomic { next } => {
+                    position = self.run(pc + 1, position, None, true)?;
+                    pc = *next;
+                }
+                Inst::Lookaround {
+                    behind,
+                    negated,
+                    next,
+                } => {
+                    let base = self.stack.len();
+                    let matched = match behind {
+                        Some((min, max)) => self.match_behind(pc + 1, position, *min, *max),
+                        None => self.run(pc + 1, position, None, true).is_some(),
+                    };
+                    // Groups of a negated lookaround never capture anything
+                    if *negated {
+                        self.undo(base);
+                        if matched {
+                            return None;
+                        }
+                    } else if !matched {
+                        return None;
+                    }
+                    pc = *next;
+                }
+            }
+            is_first = position != previous;
+        }
+    }
+
+    /// Records the value into the slot, restored when backtracking over it
+    fn save(&mut self, slot: usize, value: Option<usize>) {
+        let value = std::mem::replace(&mut self.slots[slot], value);
+        self.stack.push(Frame::RestoreSlot { slot, value });
+    }
+
+    /// Undoes the changes to the slots a matched subprogram left above `base`
+    fn undo(&mut self, base: usize) {
+        for frame in self.stack.drain(base..).rev() {
+            if let Frame::RestoreSlot { slot, value } = frame {
+                self.slots[slot] = value;
+            }
+        }
+    }
+
+    /// End of the text matched by the group if it follows at the position
+    fn match_backreference(
+        &self,
+        group: usize,
+        case_insensitive: bool,
+        position: usize,
+    ) -> Option<usize> {
+        let (Some(start), Some(end)) = (self.slots[group * 2], self.slots[group * 2 + 1]) else {
+            return None;
+        };
+        let text = &self.haystack.bytes()[start..end];
+        let rest = self.haystack.bytes().get(position..position + text.len())?;
+        let is_equal = if case_insensitive {
+            rest.eq_ignore_ascii_case(text)
+        } else {
+            rest == text
+        };
+        is_equal.then_some(position + text.len())
+    }
+
+    /// Whether the subprogram at `pc` matches text ending at the position, trying shorter
+    /// texts first. The memo of the states ending there is shared by all the starts.
+    fn match_behind(&mut self, pc: usize, position: usize, min: usize, max: usize) -> bool {
+        let mut start = position;
+        for length in 0..=max {
+            if length >= min && self.run(pc, start, Some(position), true).is_some() {
+                return true;
+            }
+            let Some((_, len)) = self.haystack.char_before(start) else {
+                return false;
+            };
+            start -= len;
+        }
+        false
+    }
+}
@@ -0,0 +1,50 @@
+use crate::parser::Parser;
+use crate::{Pattern, Syntax};
+
+/// Builds a [`Pattern`] with non default matching settings
+#[derive(Clone, Debug)]
+pub struct PatternBuilder {
+    pattern: String,
+    syntax: Syntax,
+    case_insensitive: bool,
+    unicode: bool,
+}
+
+impl PatternBuilder {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            syntax: Syntax::default(),
+            case_insensitive: false,
+            unicode: false,
+        }
+    }
+
+    /// Flavour of the pattern language, extended regular expressions by default
+    pub fn syntax(&mut self, syntax: Syntax) -> &mut Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// Match letters regardless of their case
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Match Unicode digits, letters and whitespace with `\d`, `\w` and `\s`
+    /// instead of the ASCII ones, and ignore the case of all letters, not only
+    /// the ASCII ones
+    pub fn unicode(&mut self, yes: bool) -> &mut Self {
+        self.unicode = yes;
+        self
+    }
+
+    pub fn build(&self) -> anyhow::Result<Pattern> {
+        let pattern = Parser::new(&self.pattern, self.syntax)
+            .unicode(self.unicode)
+            .case_insensitive(self.case_insensitive)
+            .parse()?;
+        Ok(Pattern::new(pattern))
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use crate::matches::Match;
+
+/// Spans matched by the capture groups of a pattern, group 0 is the whole match
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Captures {
+    spans: Vec<Option<Match>>,
+    /// Names of the groups, shared by all captures of the pattern
+    names: Arc<[Option<String>]>,
+}
+
+impl Captures {
+    pub(crate) fn new(group_count: usize) -> Self {
+        Self {
+            spans: vec![None; group_count + 1],
+            names: Arc::default(),
+        }
+    }
+
+    pub(crate) fn with_names(mut self, names: Arc<[Option<String>]>) -> Self {
+        self.names = names;
+        self
+    }
+
+    /// Span matched by the group, `None` if the group did not participate in the match
+    pub fn get(&self, group: usize) -> Option<Match> {
+        self.spans.get(group).copied().flatten()
+    }
+
+    /// Span matched by the group named `name` with `(?<name>...)`
+    pub fn name(&self, name: &str) -> Option<Match> {
+        let group = self
+            .names
+            .iter()
+            .position(|group_name| group_name.as_deref() == Some(name))?;
+        self.get(group)
+    }
+
+    /// Appends the template to `output` with group references replaced by the text
+    /// the groups matched in `input`: `$1` or `${1}` by group number and `$name` or
+    /// `${name}` by group name. Groups which did not match are replaced by nothing,
+    /// and `$$` is a literal `$`.
+    pub fn expand(&self, template: &str, input: &[u8], output: &mut Vec<u8>) {
+        let mut rest = template;
+        while let Some(dollar) = rest.find('$') {
+            output.extend_from_slice(&rest.as_bytes()[..dollar]);
+            rest = &rest[dollar + 1..];
+            if let Some(after) = rest.strip_prefix('$') {
+                output.push(b'$');
+                rest = after;
+                continue;
+            }
+
+            let (reference, after) = match rest.strip_prefix('{') {
+                Some(braced) => match braced.find('}') {
+                    Some(end) => (&braced[..end], &braced[end + 1..]),
+                    None => ("", rest),
+                },
+                None => {
+                    let end = rest
+                        .find(|ch: char| !ch.is_ascii_alphanumeric() && ch != '_')
+                        .unwrap_or(rest.len());
+                    rest.split_at(end)
+                }
+            };
+            // A `$` not followed by a reference is kept as is
+            if reference.is_empty() {
+                output.push(b'$');
+                continue;
+            }
+            rest = after;
+            let found = match reference.parse::<usize>() {
+                Ok(group) => self.get(group),
+                Err(_) => self.name(reference),
+            };
+            if let Some(found) = found {
+                output.extend_from_slice(&input[found.range()]);
+            }
+        }
+        output.extend_from_slice(rest.as_bytes());
+    }
+
+    /// Number of groups including the whole match group 0
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Option<Match>> + '_ {
+        self.spans.iter().copied()
+    }
+
+    /// Sets the span of the group, returning the previous one
+    pub(crate) fn set(&mut self, group: usize, span: Option<Match>) -> Option<Match> {
+        std::mem::replace(&mut self.spans[group], span)
+    }
+}
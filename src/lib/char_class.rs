@@ -0,0 +1,151 @@
+use std::cmp::Ordering;
+use std::ops::RangeInclusive;
+
+use crate::unicode;
+
+/// Set of characters stored as sorted inclusive ranges, which neither overlap nor touch
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CharClass {
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    pub fn new(ranges: impl IntoIterator<Item = (char, char)>) -> Self {
+        let mut class = Self {
+            ranges: ranges.into_iter().collect(),
+        };
+        class.normalize();
+        class
+    }
+
+    /// Class of the characters in the range the predicate holds for
+    pub fn from_predicate(chars: RangeInclusive<char>, predicate: impl Fn(char) -> bool) -> Self {
+        let mut ranges: Vec<(char, char)> = Vec::new();
+        for ch in chars.filter(|&ch| predicate(ch)) {
+            match ranges.last_mut() {
+                Some(last) if next_char(last.1) == Some(ch) => last.1 = ch,
+                _ => ranges.push((ch, ch)),
+            }
+        }
+        Self { ranges }
+    }
+
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub fn push(&mut self, start: char, end: char) {
+        self.ranges.push((start, end));
+        self.normalize();
+    }
+
+    pub fn union(&mut self, other: &CharClass) {
+        self.ranges.extend(&other.ranges);
+        self.normalize();
+    }
+
+    /// Characters at which the membership in the class changes, in ascending order
+    pub fn boundaries(&self) -> impl Iterator<Item = char> + '_ {
+        self.ranges
+            .iter()
+            .flat_map(|&(start, end)| [Some(start), next_char(end)])
+            .flatten()
+    }
+
+    /// Class of all the characters not in this one
+    pub fn negate(&self) -> Self {
+        let mut ranges = Vec::new();
+        let mut next = Some(char::MIN);
+        for &(start, end) in &self.ranges {
+            if let Some(next) = next.filter(|&next| next < start) {
+                ranges.push((next, previous_char(start).expect("start is above next")));
+            }
+            next = next_char(end);
+        }
+        ranges.extend(next.map(|next| (next, char::MAX)));
+        Self { ranges }
+    }
+
+    pub fn contains(&self, ch: char) -> bool {
+        self.ranges
+            .binary_search_by(|&(start, end)| {
+                if end < ch {
+                    Ordering::Less
+                } else if start > ch {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+
+    /// Adds the other case of every letter in the class, of the ASCII letters only
+    /// unless `unicode` is set
+    pub fn fold_case(&mut self, unicode: bool) {
+        if unicode {
+            let other_cases = unicode::case_pairs()
+                .iter()
+                .filter(|&&(ch, _)| self.contains(ch))
+                .map(|&(_, other)| (other, other))
+                .collect::<Vec<_>>();
+            self.ranges.extend(other_cases);
+            self.normalize();
+            return;
+        }
+        let other_cases = self
+            .ranges
+            .iter()
+            .flat_map(|&(start, end)| {
+                [('a', 'z'), ('A', 'Z')].into_iter().filter_map(
+                    move |(letters_start, letters_end)| {
+                        // Only the bounds clipped to the ASCII letters fit in a byte
+                        let start = start.max(letters_start);
+                        let end = end.min(letters_end);
+                        (start <= end).then(|| (other_case(start), other_case(end)))
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        self.ranges.extend(other_cases);
+        self.normalize();
+    }
+
+    /// Sorts the ranges and merges the overlapping or adjacent ones
+    fn normalize(&mut self) {
+        self.ranges.sort_unstable();
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(self.ranges.len());
+        for &(start, end) in &self.ranges {
+            match merged.last_mut() {
+                Some(last) if next_char(last.1).is_none_or(|next| start <= next) => {
+                    last.1 = last.1.max(end)
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+        self.ranges = merged;
+    }
+}
+
+/// Other case of the ASCII letter
+fn other_case(letter: char) -> char {
+    char::from(letter as u8 ^ 0x20)
+}
+
+/// Character following `ch`, skipping the surrogate code points
+fn next_char(ch: char) -> Option<char> {
+    match ch {
+        '\u{D7FF}' => Some('\u{E000}'),
+        char::MAX => None,
+        _ => char::from_u32(ch as u32 + 1),
+    }
+}
+
+/// Character preceding `ch`, skipping the surrogate code points
+fn previous_char(ch: char) -> Option<char> {
+    match ch {
+        '\u{E000}' => Some('\u{D7FF}'),
+        char::MIN => None,
+        _ => char::from_u32(ch as u32 - 1),
+    }
+}
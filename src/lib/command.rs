@@ -1,35 +1,241 @@
-use std::str::FromStr;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
 
 use clap::Parser;
 
-use crate::Pattern;
+use crate::searcher::{is_binary, Report, SearchOptions, Searcher};
+use crate::walk::WalkOptions;
+use crate::{PatternBuilder, Syntax};
 
 #[derive(Parser, Debug)]
 #[command(about, author, version)]
-/// Grep text by pattern from stdin
+/// Grep text by pattern from files or stdin
 pub struct Command {
-    /// Should match Regexp pattern
-    #[arg(short = 'e', default_value_t = true)]
-    regexp: bool,
+    /// Interpret the pattern as an extended regular expression, the default syntax
+    #[arg(short = 'E', long = "extended-regexp", short_alias = 'e')]
+    extended_regexp: bool,
+
+    /// Interpret `*` in the pattern as a glob-like wildcard matching one or more characters
+    #[arg(long = "glob", conflicts_with = "extended_regexp")]
+    glob: bool,
+
+    /// Interpret the pattern as a Perl-compatible regular expression, which supports
+    /// lookahead and lookbehind
+    #[arg(
+        short = 'P',
+        long = "perl-regexp",
+        conflicts_with_all = ["extended_regexp", "glob"]
+    )]
+    perl_regexp: bool,
+
+    /// Search directories recursively, following only symbolic links given on the command line
+    #[arg(short = 'r', long = "recursive")]
+    recursive: bool,
+
+    /// Search directories recursively, following all symbolic links
+    #[arg(short = 'R', long = "dereference-recursive")]
+    dereference_recursive: bool,
+
+    /// Search only files whose name matches the glob
+    #[arg(long = "include", value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files whose name matches the glob
+    #[arg(long = "exclude", value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Search binary files as if they were text
+    #[arg(short = 'a', long = "text")]
+    text: bool,
+
+    /// Print only the matched parts of the lines, each on its own line
+    #[arg(short = 'o', long = "only-matching")]
+    only_matching: bool,
+
+    /// Ignore case distinctions in the pattern and the input
+    #[arg(short = 'i', long = "ignore-case")]
+    ignore_case: bool,
+
+    /// Match Unicode digits, letters and whitespace with \d, \w and \s instead of ASCII ones,
+    /// and ignore the case of non-ASCII letters too
+    #[arg(long = "unicode")]
+    unicode: bool,
+
+    /// Print every match replaced by TEMPLATE, where $1 or ${name} stand for the text
+    /// of a capture group
+    #[arg(long = "replace", value_name = "TEMPLATE")]
+    replace: Option<String>,
+
+    /// Select the lines that do not match the pattern
+    #[arg(short = 'v', long = "invert-match")]
+    invert_match: bool,
+
+    /// Print NUM lines of context after every selected line
+    #[arg(short = 'A', long = "after-context", value_name = "NUM")]
+    after_context: Option<usize>,
+
+    /// Print NUM lines of context before every selected line
+    #[arg(short = 'B', long = "before-context", value_name = "NUM")]
+    before_context: Option<usize>,
+
+    /// Print NUM lines of context around every selected line
+    #[arg(short = 'C', long = "context", value_name = "NUM")]
+    context: Option<usize>,
+
+    /// Print only the number of selected lines of every input
+    #[arg(short = 'c', long = "count")]
+    count: bool,
+
+    /// Print only the names of inputs with selected lines
+    #[arg(short = 'l', long = "files-with-matches")]
+    files_with_matches: bool,
+
+    /// Print only the names of inputs without selected lines
+    #[arg(
+        short = 'L',
+        long = "files-without-match",
+        conflicts_with = "files_with_matches"
+    )]
+    files_without_match: bool,
 
     /// Pattern to grep from the input
     pattern: String,
+
+    /// Files or directories to search, stdin is read when none given
+    files: Vec<PathBuf>,
+}
+
+/// Outcome of the whole search, turned into the exit code
+#[derive(Default)]
+struct Status {
+    matched: bool,
+    had_error: bool,
 }
 
 impl Command {
     pub fn invoke(&self) -> anyhow::Result<i32> {
-        let mut input_line = String::new();
+        let syntax = if self.glob {
+            Syntax::Glob
+        } else if self.perl_regexp {
+            Syntax::Perl
+        } else {
+            Syntax::Extended
+        };
+        let pattern = PatternBuilder::new(&self.pattern)
+            .syntax(syntax)
+            .case_insensitive(self.ignore_case)
+            .unicode(self.unicode)
+            .build()?;
+        let recursive = self.recursive || self.dereference_recursive;
+        // Recursive search without files means the working directory
+        let implicit_root = self.files.is_empty();
+        let roots = if implicit_root {
+            vec![PathBuf::from(".")]
+        } else {
+            self.files.clone()
+        };
+
+        let options = SearchOptions {
+            only_matching: self.only_matching,
+            invert_match: self.invert_match,
+            report: self.report(),
+            with_filename: roots.len() > 1 || (recursive && roots[0].is_dir()),
+            after_context: self.after_context.or(self.context).unwrap_or(0),
+            before_context: self.before_context.or(self.context).unwrap_or(0),
+            replace: self.replace.clone(),
+        };
+        let mut searcher = Searcher::new(pattern, options);
 
-        std::io::stdin().read_line(&mut input_line).unwrap();
+        let stdout = io::stdout();
+        let mut output = io::BufWriter::new(stdout.lock());
+        let mut status = Status::default();
 
-        let input_line = input_line.trim_end();
-        let mut pattern = Pattern::from_str(&self.pattern).unwrap();
-        let res = pattern.match_line(input_line)?;
+        if self.files.is_empty() && !recursive {
+            let stdin = io::stdin();
+            status.matched = searcher.search(stdin.lock(), "(standard input)", &mut output)?;
+        } else {
+            let walk_options = WalkOptions {
+                follow_links: self.dereference_recursive,
+                include: self.include.clone(),
+                exclude: self.exclude.clone(),
+            };
+            for root in &roots {
+                if !root.is_dir() {
+                    if walk_options.is_file_selected(root) {
+                        self.search_file(&mut searcher, root, &mut output, &mut status);
+                    }
+                    continue;
+                }
+                if !recursive {
+                    eprintln!("grepr: {}: Is a directory", root.display());
+                    status.had_error = true;
+                    continue;
+                }
 
-        if res {
+                for entry in walk_options.walk(root) {
+                    match entry {
+                        Ok(path) => {
+                            let path = if implicit_root {
+                                path.strip_prefix(root).unwrap_or(&path)
+                            } else {
+                                &path
+                            };
+                            self.search_file(&mut searcher, path, &mut output, &mut status);
+                        }
+                        Err((path, e)) => {
+                            eprintln!("grepr: {}: {e}", path.display());
+                            status.had_error = true;
+                        }
+                    }
+                }
+            }
+        }
+        output.flush()?;
+
+        if status.had_error {
+            Ok(2)
+        } else if status.matched {
             Ok(0)
         } else {
             Ok(1)
         }
     }
+
+    fn report(&self) -> Report {
+        if self.files_with_matches {
+            Report::FilesWithMatches
+        } else if self.files_without_match {
+            Report::FilesWithoutMatch
+        } else if self.count {
+            Report::Count
+        } else {
+            Report::Lines
+        }
+    }
+
+    fn search_file(
+        &self,
+        searcher: &mut Searcher,
+        path: &Path,
+        output: &mut impl Write,
+        status: &mut Status,
+    ) {
+        let res = File::open(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| {
+                let mut input = BufReader::new(file);
+                if !self.text && is_binary(&mut input)? {
+                    return Ok(false);
+                }
+                searcher.search(input, &path.display().to_string(), output)
+            });
+        match res {
+            Ok(matched) => status.matched |= matched,
+            Err(e) => {
+                eprintln!("grepr: {}: {e}", path.display());
+                status.had_error = true;
+            }
+        }
+    }
 }
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use crate::haystack::Haystack;
+use crate::nfa::{Inst, Program};
+use crate::token::CharToken;
+
+/// Cached states are dropped once there are more of them, which bounds the memory
+const MAX_CACHED_STATES: usize = 4096;
+
+/// Transition which has not been computed yet
+const UNKNOWN: u32 = u32::MAX;
+
+/// Set of NFA instructions alive after some input: consuming instructions, `Match`
+/// and end assertions, which stay unresolved until the end of input
+struct State {
+    insts: Vec<usize>,
+    is_match: bool,
+}
+
+/// Partition of all characters into classes that no instruction of the program tells
+/// apart, so the DFA needs a transition per class instead of per character
+struct CharClasses {
+    /// First characters of every class but the first one, which starts at `'\0'`
+    boundaries: Vec<char>,
+    /// Class of every ASCII character, looked up without a search
+    ascii: [u32; 128],
+}
+
+impl CharClasses {
+    fn new(program: &Program) -> Self {
+        let mut boundaries = program
+            .insts
+            .iter()
+            .filter_map(|inst| match inst {
+                Inst::Char(token) => Some(token.class()),
+                _ => None,
+            })
+            .flat_map(|class| class.boundaries().collect::<Vec<_>>())
+            .filter(|&boundary| boundary != '\0')
+            .collect::<Vec<_>>();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut ascii = [0; 128];
+        for (byte, class) in ascii.iter_mut().enumerate() {
+            *class =
+                boundaries.partition_point(|&boundary| boundary <= char::from(byte as u8)) as u32;
+        }
+        Self { boundaries, ascii }
+    }
+
+    fn len(&self) -> usize {
+        self.boundaries.len() + 1
+    }
+
+    fn get(&self, ch: char) -> u32 {
+        match self.ascii.get(ch as usize) {
+            Some(&class) => class,
+            None => self.boundaries.partition_point(|&boundary| boundary <= ch) as u32,
+        }
+    }
+
+    /// Character standing for all the characters of the class
+    fn representative(&self, class: u32) -> char {
+        match class {
+            0 => '\0',
+            _ => self.boundaries[class as usize - 1],
+        }
+    }
+}
+
+/// DFA built lazily from the NFA program while searching: every DFA state is a set of
+/// NFA instructions, and transitions between them are computed on first use.
+/// Answers only whether the input matches, which needs a single pass over the input.
+pub(crate) struct LazyDfa {
+    classes: CharClasses,
+    states: Vec<State>,
+    /// Transitions of every state, one for each character class
+    transitions: Vec<u32>,
+    state_ids: HashMap<Vec<usize>, u32>,
+    /// Instructions starting a new match attempt at a position other than 0
+    restart: Vec<usize>,
+    /// Incremented every time the cache is cleared, which invalidates state ids
+    generation: usize,
+}
+
+impl LazyDfa {
+    /// Returns `None` if the program uses assertions the DFA can not track, like word
+    /// boundaries or line anchors which depend on the previous character
+    pub fn new(program: &Program) -> Option<Self> {
+        let supported = program.insts.iter().all(|inst| match inst {
+            Inst::Assert(token) => is_start_assertion(token) || is_end_assertion(token),
+            _ => true,
+        });
+        if !supported {
+            return None;
+        }
+
+        Some(Self {
+            classes: CharClasses::new(program),
+            states: Vec::new(),
+            transitions: Vec::new(),
+            state_ids: HashMap::new(),
+            restart: closure(program, &[0], false, false),
+            generation: 0,
+        })
+    }
+
+    pub fn is_match(&mut self, program: &Program, haystack: Haystack) -> bool {
+        let is_empty = haystack.is_empty();
+        let start = closure(program, &[0], true, is_empty);
+        let mut state = self.state_id(program, start);
+
+        for ch in haystack.chars() {
+            if self.states[state as usize].is_match {
+                return true;
+            }
+            let class = self.classes.get(ch);
+            let index = state as usize * self.classes.len() + class as usize;
+            let mut next = self.transitions[index];
+            if next == UNKNOWN {
+                let generation = self.generation;
+                next = self.compute_transition(program, state, class);
+                if generation == self.generation {
+                    self.transitions[index] = next;
+                }
+            }
+            state = next;
+            if self.states[state as usize].insts.is_empty() {
+                return false;
+            }
+        }
+
+        self.states[state as usize].is_match || self.is_match_at_end(program, state, is_empty)
+    }
+
+    fn is_match_at_end(&self, program: &Program, state: u32, is_empty: bool) -> bool {
+        let after_end_assertions = self.states[state as usize]
+            .insts
+            .iter()
+            .filter(
+                |&&pc| matches!(&program.insts[pc], Inst::Assert(token) if is_end_assertion(token)),
+            )
+            .map(|pc| pc + 1)
+            .collect::<Vec<_>>();
+        closure(program, &after_end_assertions, is_empty, true)
+            .iter()
+            .any(|&pc| matches!(program.insts[pc], Inst::Match))
+    }
+
+    fn compute_transition(&mut self, program: &Program, state: u32, class: u32) -> u32 {
+        let ch = self.classes.representative(class);
+        let stepped = self.states[state as usize]
+            .insts
+            .iter()
+            .filter(|&&pc| match &program.insts[pc] {
+                Inst::Char(token) => token.match_char(ch),
+                _ => false,
+            })
+            .map(|pc| pc + 1)
+            .collect::<Vec<_>>();
+
+        let mut next = closure(program, &stepped, false, false);
+        next.extend(&self.restart);
+        next.sort_unstable();
+        next.dedup();
+        self.state_id(program, next)
+    }
+
+    fn state_id(&mut self, program: &Program, insts: Vec<usize>) -> u32 {
+        if let Some(&id) = self.state_ids.get(&insts) {
+            return id;
+        }
+        if self.states.len() >= MAX_CACHED_STATES {
+            self.states.clear();
+            self.transitions.clear();
+            self.state_ids.clear();
+            self.generation += 1;
+        }
+
+        let id = self.states.len() as u32;
+        let is_match = insts
+            .iter()
+            .any(|&pc| matches!(program.insts[pc], Inst::Match));
+        self.state_ids.insert(insts.clone(), id);
+        self.states.push(State { insts, is_match });
+        self.transitions
+            .extend(std::iter::repeat_n(UNKNOWN, self.classes.len()));
+        id
+    }
+}
+
+/// Follows the instructions not consuming input from the given ones, returns the sorted
+/// set of reached instructions that consume input, match or wait for the end of input
+fn closure(program: &Program, from: &[usize], at_start: bool, at_end: bool) -> Vec<usize> {
+    let mut reached = Vec::new();
+    let mut stack = from.to_vec();
+    let mut seen = vec![false; program.insts.len()];
+    while let Some(pc) = stack.pop() {
+        if std::mem::replace(&mut seen[pc], true) {
+            continue;
+        }
+        match &program.insts[pc] {
+            Inst::Jump(to) => stack.push(*to),
+            Inst::Split(first, second) => stack.extend([*first, *second]),
+            Inst::Save(_) => stack.push(pc + 1),
+            Inst::Assert(token) if is_start_assertion(token) => {
+                if at_start {
+                    stack.push(pc + 1);
+                }
+            }
+            Inst::Assert(token) if at_end && is_end_assertion(token) => stack.push(pc + 1),
+            Inst::Char(_) | Inst::Match | Inst::Assert(_) => reached.push(pc),
+            inst => unreachable!("{inst:?} is run only by the backtracker"),
+        }
+    }
+    reached.sort_unstable();
+    reached
+}
+
+fn is_start_assertion(token: &CharToken) -> bool {
+    matches!(token, CharToken::StartText)
+}
+
+fn is_end_assertion(token: &CharToken) -> bool {
+    matches!(token, CharToken::EndText)
+}
@@ -0,0 +1,80 @@
+/// Matches a file name against a shell glob supporting `*`, `?`, `[...]` and `\` escapes.
+/// On a mismatch only the last `*` takes one more character, as the ones before it can
+/// not help, so the time is proportional to the product of the lengths.
+pub(crate) fn glob_match(glob: &str, name: &str) -> bool {
+    let (glob, name) = (glob.as_bytes(), name.as_bytes());
+    let (mut glob_pos, mut name_pos) = (0, 0);
+    // Glob offset after the last `*` and the name offset the text it skips ends at
+    let mut last_star = None;
+    while glob_pos < glob.len() || name_pos < name.len() {
+        if glob.get(glob_pos) == Some(&b'*') {
+            glob_pos += 1;
+            last_star = Some((glob_pos, name_pos));
+            continue;
+        }
+        let matched_len = name
+            .get(name_pos)
+            .and_then(|&name_ch| match_one(&glob[glob_pos..], name_ch));
+        if let Some(len) = matched_len {
+            glob_pos += len;
+            name_pos += 1;
+            continue;
+        }
+        match last_star {
+            Some((star_glob_pos, skipped_to)) if skipped_to < name.len() => {
+                last_star = Some((star_glob_pos, skipped_to + 1));
+                (glob_pos, name_pos) = (star_glob_pos, skipped_to + 1);
+            }
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Length of the glob element at the start of the glob if it matches the character
+fn match_one(glob: &[u8], name_ch: u8) -> Option<usize> {
+    let (&ch, rest) = glob.split_first()?;
+    let (is_match, len) = match ch {
+        b'?' => (true, 1),
+        b'[' => match match_bracket(rest) {
+            Some((set, negated, after)) => (
+                set_contains(set, name_ch) != negated,
+                glob.len() - after.len(),
+            ),
+            // Unclosed bracket is matched literally
+            None => (name_ch == b'[', 1),
+        },
+        b'\\' if !rest.is_empty() => (name_ch == rest[0], 2),
+        _ => (name_ch == ch, 1),
+    };
+    is_match.then_some(len)
+}
+
+/// Splits `[...]` body from the rest of the glob, returns `None` if bracket is not closed
+fn match_bracket(glob: &[u8]) -> Option<(&[u8], bool, &[u8])> {
+    let (negated, glob) = match glob.first() {
+        Some(b'!' | b'^') => (true, &glob[1..]),
+        _ => (false, glob),
+    };
+    // `]` right after the opening bracket is a literal
+    let end = glob.iter().skip(1).position(|&c| c == b']')? + 1;
+    Some((&glob[..end], negated, &glob[end + 1..]))
+}
+
+fn set_contains(set: &[u8], ch: u8) -> bool {
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == b'-' {
+            if (set[i]..=set[i + 2]).contains(&ch) {
+                return true;
+            }
+            i += 3;
+        } else {
+            if set[i] == ch {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
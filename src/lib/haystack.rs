@@ -0,0 +1,73 @@
+/// Searched input, read as characters: decoded as UTF-8 when the input is valid UTF-8,
+/// otherwise every byte is a character of the same code point, like in Latin-1
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Haystack<'h> {
+    bytes: &'h [u8],
+    /// The same input as text, if it is valid UTF-8
+    text: Option<&'h str>,
+}
+
+impl<'h> Haystack<'h> {
+    pub fn new(bytes: &'h [u8]) -> Self {
+        Self {
+            bytes,
+            text: std::str::from_utf8(bytes).ok(),
+        }
+    }
+
+    pub fn from_str(text: &'h str) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            text: Some(text),
+        }
+    }
+
+    pub fn bytes(&self) -> &'h [u8] {
+        self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Character starting at the byte offset along with its length in bytes,
+    /// `None` at the end of input
+    pub fn char_at(&self, position: usize) -> Option<(char, usize)> {
+        match self.text {
+            Some(text) => {
+                let ch = text.get(position..)?.chars().next()?;
+                Some((ch, ch.len_utf8()))
+            }
+            None => self.bytes.get(position).map(|&byte| (char::from(byte), 1)),
+        }
+    }
+
+    /// Character ending at the byte offset along with its length in bytes,
+    /// `None` at the start of input
+    pub fn char_before(&self, position: usize) -> Option<(char, usize)> {
+        match self.text {
+            Some(text) => {
+                let ch = text.get(..position)?.chars().next_back()?;
+                Some((ch, ch.len_utf8()))
+            }
+            None => position
+                .checked_sub(1)
+                .and_then(|position| self.bytes.get(position))
+                .map(|&byte| (char::from(byte), 1)),
+        }
+    }
+
+    /// Iterates over the characters of the input
+    pub fn chars(&self) -> impl Iterator<Item = char> + 'h {
+        let (text, bytes) = match self.text {
+            Some(text) => (text, &[][..]),
+            None => ("", self.bytes),
+        };
+        text.chars()
+            .chain(bytes.iter().map(|&byte| char::from(byte)))
+    }
+}
@@ -0,0 +1,103 @@
+use std::ops::Range;
+
+use crate::captures::Captures;
+use crate::haystack::Haystack;
+use crate::Pattern;
+
+/// Single match of a pattern, as byte offsets into the searched input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Match {
+    start: usize,
+    end: usize,
+}
+
+impl Match {
+    pub(crate) fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Iterator over the captures of non-overlapping matches, created by
+/// [`Pattern::captures_iter`]
+pub struct CaptureMatches<'p, 'h> {
+    pattern: &'p mut Pattern,
+    haystack: Haystack<'h>,
+    position: usize,
+    last_end: Option<usize>,
+}
+
+impl<'p, 'h> CaptureMatches<'p, 'h> {
+    pub(crate) fn new(pattern: &'p mut Pattern, haystack: Haystack<'h>) -> Self {
+        Self {
+            pattern,
+            haystack,
+            position: 0,
+            last_end: None,
+        }
+    }
+}
+
+impl Iterator for CaptureMatches<'_, '_> {
+    type Item = Captures;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.position > self.haystack.len() {
+                return None;
+            }
+            let captures = self.pattern.captures_at(self.haystack, self.position)?;
+            let found = captures.get(0)?;
+
+            if found.is_empty() {
+                // Step over one character so an empty match is not found again
+                let next_char_len = self.haystack.char_at(found.end()).map_or(1, |(_, len)| len);
+                self.position = found.end() + next_char_len;
+                // Empty match right after the previous match is a part of it
+                if self.last_end == Some(found.end()) {
+                    continue;
+                }
+            } else {
+                self.position = found.end();
+            }
+            self.last_end = Some(found.end());
+            return Some(captures);
+        }
+    }
+}
+
+/// Iterator over non-overlapping matches, created by [`Pattern::find_iter`]
+pub struct FindMatches<'p, 'h>(CaptureMatches<'p, 'h>);
+
+impl<'p, 'h> FindMatches<'p, 'h> {
+    pub(crate) fn new(pattern: &'p mut Pattern, haystack: Haystack<'h>) -> Self {
+        Self(CaptureMatches::new(pattern, haystack))
+    }
+}
+
+impl Iterator for FindMatches<'_, '_> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()?.get(0)
+    }
+}
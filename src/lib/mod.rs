@@ -1,10 +1,30 @@
+mod backtrack;
+mod builder;
+mod captures;
+mod char_class;
 mod command;
+mod dfa;
+mod glob;
+mod haystack;
+mod matches;
+mod nfa;
+mod parser;
 mod pattern;
 mod pattern_item;
 mod pattern_list;
+mod pikevm;
+mod searcher;
+mod syntax;
 mod text_token;
 mod token;
 mod token_modifier;
+mod unicode;
+mod walk;
 
+pub use builder::PatternBuilder;
+pub use captures::Captures;
+pub use matches::{CaptureMatches, FindMatches, Match};
+pub use parser::ParseError;
 pub use pattern::Pattern;
+pub use syntax::Syntax;
 pub use command::Command;
@@ -0,0 +1,381 @@
+use std::slice;
+
+use crate::haystack::Haystack;
+use crate::pattern_item::PatternItem;
+use crate::pattern_list::{token_length_bounds, PatternList};
+use crate::text_token::TextToken;
+use crate::token::{is_word_char, CharToken, Token};
+use crate::token_modifier::Greediness;
+
+/// Most instructions a pattern may compile to, so that counted repetitions of a pattern
+/// from an untrusted source can not take all the memory
+pub(crate) const MAX_PROGRAM_SIZE: usize = 1 << 21;
+
+/// Single instruction of a Thompson NFA
+#[derive(Clone, Debug)]
+pub(crate) enum Inst {
+    /// Consumes one character matched by the token
+    Char(CharToken),
+    /// Fails if the next character is matched by the token, so a possessive repetition of
+    /// it is left only once it can not repeat
+    NotChar(CharToken),
+    /// Checks the current position without consuming input
+    Assert(CharToken),
+    /// Continues with both instructions, the first one is preferred
+    Split(usize, usize),
+    Jump(usize),
+    /// Records the current position into the capture slot
+    Save(usize),
+    Match,
+    /// Records the span of the capture group, from the start kept in the `start` slot to
+    /// the current position. Backreferences inside the group see the previous span until
+    /// the group ends.
+    SaveGroup {
+        group: usize,
+        start: usize,
+    },
+    /// Consumes the text previously matched by the capture group
+    Backreference {
+        group: usize,
+        case_insensitive: bool,
+    },
+    /// Matches the subprogram starting at the next instruction and ending with its own
+    /// `Match` in the first way it can, then continues at `next`
+    Atomic {
+        next: usize,
+    },
+    /// Checks that the subprogram starting at the next instruction matches right after
+    /// the position, then continues at `next`. A lookbehind holds the bounds of the
+    /// subprogram match length instead, and checks that it matches right before.
+    Lookaround {
+        behind: Option<(usize, usize)>,
+        negated: bool,
+        next: usize,
+    },
+}
+
+/// Pattern compiled to a list of NFA instructions, starting at instruction 0
+#[derive(Debug)]
+pub(crate) struct Program {
+    pub insts: Vec<Inst>,
+    /// Two slots, start and end, for every group including the whole match group 0,
+    /// followed by the slots which only the backtracker uses
+    pub slot_count: usize,
+    /// Whether the pattern can only match at the start of the input
+    pub is_start_anchored: bool,
+    /// Whether the program uses instructions which only the backtracker runs, because
+    /// the pattern has features that an automaton can not express
+    pub needs_backtracking: bool,
+    /// Whether the program has backreferences, so whether it matches from an instruction
+    /// depends on the captured text and not only on the position
+    pub has_backreferences: bool,
+}
+
+impl Program {
+    /// Compiles the pattern, with the instructions that only the backtracker runs if the
+    /// pattern needs them
+    pub fn compile(pattern: &PatternList, group_count: usize) -> Self {
+        let has_backreferences = pattern.max_backreference().is_some();
+        let mut compiler = Compiler {
+            insts: Vec::new(),
+            slot_count: (group_count + 1) * 2,
+            has_backreferences,
+        };
+        compiler.push(Inst::Save(0));
+        compiler.compile_items(pattern.items());
+        compiler.push(Inst::Save(1));
+        compiler.push(Inst::Match);
+        Self {
+            insts: compiler.insts,
+            slot_count: compiler.slot_count,
+            is_start_anchored: pattern.is_start_anchored(),
+            needs_backtracking: needs_backtracking(pattern.items()),
+            has_backreferences,
+        }
+    }
+}
+
+/// Whether any of the items has a feature that an automaton can not express
+fn needs_backtracking(items: &[PatternItem]) -> bool {
+    items.iter().any(|item| {
+        item.greediness == Greediness::Possessive
+            || match &item.token {
+                Token::Char(_) => false,
+                // Matching text captured earlier needs memory an automaton does not have,
+                // checking another pattern at the same position needs a separate match and
+                // threads of an automaton can not be cut off once a subpattern matched
+                Token::Text(
+                    TextToken::Backreference { .. }
+                    | TextToken::Lookaround { .. }
+                    | TextToken::Atomic(_),
+                ) => true,
+                Token::Text(token) => token
+                    .subpatterns()
+                    .iter()
+                    .any(|pattern| needs_backtracking(pattern.items())),
+            }
+    })
+}
+
+/// Number of instructions the items compile to, not counting the few ones which only the
+/// backtracker needs. Saturates instead of overflowing.
+pub(crate) fn program_size(items: &[PatternItem]) -> usize {
+    items
+        .iter()
+        .map(item_program_size)
+        .fold(0, usize::saturating_add)
+}
+
+fn item_program_size(item: &PatternItem) -> usize {
+    let token_size = match &item.token {
+        Token::Char(_) | Token::Text(TextToken::Backreference { .. }) => 1,
+        Token::Text(TextToken::Alteration(variants)) => variants
+            .iter()
+            // Every variant but the last one starts with a split, all end with a jump
+            .map(|variant| program_size(variant.items()).saturating_add(2))
+            .fold(0, usize::saturating_add),
+        Token::Text(
+            TextToken::Group { pattern, .. }
+            | TextToken::Atomic(pattern)
+            | TextToken::Lookaround { pattern, .. },
+        ) => program_size(pattern.items()).saturating_add(2),
+    };
+    // A copy of the token for every repetition, and a split for every optional one
+    let (copies, splits) = match item.max {
+        None => (item.min.max(1), 2),
+        Some(max) => (max, max - item.min),
+    };
+    token_size.saturating_mul(copies).saturating_add(splits)
+}
+
+struct Compiler {
+    insts: Vec<Inst>,
+    slot_count: usize,
+    /// Whether groups keep their previous span for the backreferences inside them
+    has_backreferences: bool,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> usize {
+        self.insts.push(inst);
+        self.insts.len() - 1
+    }
+
+    /// Pushes a split preferring the next instruction, its second target is patched later
+    fn push_split(&mut self) -> usize {
+        let pc = self.next_pc();
+        self.push(Inst::Split(pc + 1, 0))
+    }
+
+    /// Adds a slot after the capture slots
+    fn push_slot(&mut self) -> usize {
+        self.slot_count += 1;
+        self.slot_count - 1
+    }
+
+    fn next_pc(&self) -> usize {
+        self.insts.len()
+    }
+
+    /// Points a previously pushed placeholder jump or split at the target
+    fn patch(&mut self, pc: usize, target: usize) {
+        match &mut self.insts[pc] {
+            Inst::Jump(to) => *to = target,
+            Inst::Split(_, to) => *to = target,
+            Inst::Atomic { next } | Inst::Lookaround { next, .. } => *next = target,
+            _ => unreachable!("only jumps, splits and subprograms are patched"),
+        }
+    }
+
+    fn compile_items(&mut self, items: &[PatternItem]) {
+        items.iter().for_each(|item| self.compile_item(item));
+    }
+
+    /// Compiles the items as a subprogram ending with its own `Match`, right after
+    /// the instruction running it, and points the instruction past the subprogram
+    fn compile_subprogram(&mut self, pc: usize, items: &[PatternItem]) {
+        self.compile_items(items);
+        self.push(Inst::Match);
+        let next = self.next_pc();
+        self.patch(pc, next);
+    }
+
+    /// Repetition `x{min,}` is compiled as `min - 1` copies of `x` followed by the `x+` loop.
+    /// Without minimum it is a single split entering `x` again after every iteration, so an
+    /// iteration starting where the previous one ended is cut off at the split already
+    /// followed there, like in the regex crate. If `x` can match empty, that would put its
+    /// empty iterations before leaving the loop, so it is compiled as `(x+)?` instead.
+    ///
+    /// Repetition `x{min,max}` is compiled as `min` copies of `x`, followed by
+    /// `max - min` nested optional copies `(x(x)?)?`
+    ///
+    /// Lazy repetition prefers leaving over repeating in all its splits. Possessive
+    /// repetition of a character is the greedy one leaving its splits through a
+    /// `NotChar` check, other possessive repetition is the greedy one matched as an
+    /// atomic subprogram.
+    fn compile_item(&mut self, item: &PatternItem) {
+        let possessive_char = match &item.token {
+            _ if item.greediness != Greediness::Possessive => None,
+            Token::Char(token) if !token.is_assertion() => Some(token),
+            _ => {
+                let atomic = self.push(Inst::Atomic { next: 0 });
+                let greedy = PatternItem {
+                    greediness: Greediness::Greedy,
+                    ..item.clone()
+                };
+                self.compile_subprogram(atomic, slice::from_ref(&greedy));
+                return;
+            }
+        };
+
+        let mut splits = Vec::new();
+        match item.max {
+            None => {
+                for _ in 1..item.min {
+                    self.compile_token(&item.token);
+                }
+                let is_nullable = token_length_bounds(&item.token).0 == 0;
+                if item.min == 0 && !is_nullable {
+                    let split = self.push_split();
+                    self.compile_token(&item.token);
+                    self.push(Inst::Jump(split));
+                    splits.push(split);
+                } else {
+                    splits.extend((item.min == 0).then(|| self.push_split()));
+                    let start = self.next_pc();
+                    self.compile_token(&item.token);
+                    splits.push(self.push(Inst::Split(start, 0)));
+                }
+            }
+            Some(max) => {
+                for _ in 0..item.min {
+                    self.compile_token(&item.token);
+                }
+                for _ in item.min..max {
+                    splits.push(self.push_split());
+                    self.compile_token(&item.token);
+                }
+            }
+        }
+
+        if let Some(token) = possessive_char.filter(|_| !splits.is_empty()) {
+            // The last of the bounded copies leaves without the check
+            let jump = item.max.is_some().then(|| self.push(Inst::Jump(0)));
+            let check = self.push(Inst::NotChar(token.clone()));
+            for split in splits.drain(..) {
+                self.patch(split, check);
+            }
+            let end = self.next_pc();
+            jump.into_iter().for_each(|jump| self.patch(jump, end));
+        }
+
+        let end = self.next_pc();
+        for split in splits {
+            self.patch(split, end);
+            if item.greediness == Greediness::Lazy {
+                let Inst::Split(first, second) = &mut self.insts[split] else {
+                    unreachable!("repetition is compiled with splits");
+                };
+                std::mem::swap(first, second);
+            }
+        }
+    }
+
+    fn compile_token(&mut self, token: &Token) {
+        match token {
+            Token::Char(token) if token.is_assertion() => {
+                self.push(Inst::Assert(token.clone()));
+            }
+            Token::Char(token) => {
+                self.push(Inst::Char(token.clone()));
+            }
+            Token::Text(TextToken::Group { index, pattern, .. }) if self.has_backreferences => {
+                let start = self.push_slot();
+                self.push(Inst::Save(start));
+                self.compile_items(pattern.items());
+                self.push(Inst::SaveGroup {
+                    group: *index,
+                    start,
+                });
+            }
+            Token::Text(TextToken::Group { index, pattern, .. }) => {
+                self.push(Inst::Save(index * 2));
+                self.compile_items(pattern.items());
+                self.push(Inst::Save(index * 2 + 1));
+            }
+            Token::Text(TextToken::Alteration(variants)) => {
+                let mut jumps = Vec::new();
+                for (i, variant) in variants.iter().enumerate() {
+                    let split = (i + 1 < variants.len()).then(|| self.push_split());
+                    self.compile_items(variant.items());
+                    jumps.push(self.push(Inst::Jump(0)));
+                    if let Some(split) = split {
+                        let next_variant = self.next_pc();
+                        self.patch(split, next_variant);
+                    }
+                }
+                let end = self.next_pc();
+                jumps.into_iter().for_each(|jump| self.patch(jump, end));
+            }
+            Token::Text(TextToken::Backreference {
+                group,
+                case_insensitive,
+            }) => {
+                self.push(Inst::Backreference {
+                    group: *group,
+                    case_insensitive: *case_insensitive,
+                });
+            }
+            Token::Text(TextToken::Atomic(pattern)) => {
+                let atomic = self.push(Inst::Atomic { next: 0 });
+                self.compile_subprogram(atomic, pattern.items());
+            }
+            Token::Text(TextToken::Lookaround {
+                behind,
+                negated,
+                pattern,
+            }) => {
+                let behind = behind.then(|| {
+                    let (min, max) = pattern.length_bounds();
+                    (min, max.expect("lookbehind pattern has a bounded length"))
+                });
+                let lookaround = self.push(Inst::Lookaround {
+                    behind,
+                    negated: *negated,
+                    next: 0,
+                });
+                self.compile_subprogram(lookaround, pattern.items());
+            }
+        }
+    }
+}
+
+/// Whether the assertion holds at the position of the input
+pub(crate) fn check_assertion(token: &CharToken, haystack: Haystack, position: usize) -> bool {
+    let is_word_before = |unicode| {
+        haystack
+            .char_before(position)
+            .is_some_and(|(ch, _)| is_word_char(ch, unicode))
+    };
+    let is_word_after = |unicode| {
+        haystack
+            .char_at(position)
+            .is_some_and(|(ch, _)| is_word_char(ch, unicode))
+    };
+    match token {
+        CharToken::StartText => position == 0,
+        CharToken::EndText => position == haystack.len(),
+        CharToken::StartLine => position == 0 || haystack.bytes()[position - 1] == b'\n',
+        CharToken::EndLine => haystack
+            .bytes()
+            .get(position)
+            .is_none_or(|&byte| byte == b'\n'),
+        CharToken::WordBoundary { unicode } => is_word_before(*unicode) != is_word_after(*unicode),
+        CharToken::NotWordBoundary { unicode } => {
+            is_word_before(*unicode) == is_word_after(*unicode)
+        }
+        CharToken::WordStart { unicode } => !is_word_before(*unicode) && is_word_after(*unicode),
+        CharToken::WordEnd { unicode } => is_word_before(*unicode) && !is_word_after(*unicode),
+        _ => unreachable!("{token:?} is not an assertion"),
+    }
+}
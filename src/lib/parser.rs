@@ -0,0 +1,772 @@
+use std::fmt;
+use std::ops::Range;
+
+use std::slice;
+
+use crate::char_class::CharClass;
+use crate::nfa::{program_size, MAX_PROGRAM_SIZE};
+use crate::pattern_item::PatternItem;
+use crate::pattern_list::PatternList;
+use crate::syntax::Syntax;
+use crate::text_token::TextToken;
+use crate::token::{CharToken, CharType};
+use crate::token_modifier::{Greediness, TokenModifier};
+use crate::unicode;
+
+/// Error in the pattern syntax, pointing at the offending part of the pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    message: String,
+    pattern: String,
+    span: Range<usize>,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, pattern: &str, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            pattern: pattern.to_string(),
+            span,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Byte offsets of the offending part of the pattern
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let offset = self.pattern[..self.span.start].chars().count();
+        let width = self.pattern[self.span.clone()].chars().count().max(1);
+        writeln!(f, "{}", self.message)?;
+        writeln!(f, "    {}", self.pattern)?;
+        write!(f, "    {}{}", " ".repeat(offset), "^".repeat(width))
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type ParseResult<T> = Result<T, ParseError>;
+
+/// Recursive descent parser of the pattern syntax:
+///
+/// ```text
+/// alteration := concat ('|' concat)*
+/// concat     := repeat*
+/// repeat     := atom quantifier*
+/// atom       := '(' group ')' | '(?' flags ')' | '[' class ']' | '\' escape | '.' | '^' | '$' | char
+/// group      := ('?:' | '?>' | '?<' name '>' | '?P<' name '>' | '?' flags ':' | lookaround)? alteration
+/// lookaround := '?=' | '?!' | '?<=' | '?<!'
+/// flags      := [imsx]* ('-' [imsx]*)?
+/// quantifier := ('?' | '*' | '+' | '{' n '}' | '{' n ',' '}' | '{' n ',' m '}') ('?' | '+')?
+/// ```
+///
+/// A quantifier followed by `?` is lazy, followed by `+` is possessive. Lookarounds are
+/// allowed only with the [`Syntax::Perl`] syntax. An escaped letter must be a known escape,
+/// while any punctuation or space escapes itself.
+///
+/// With the [`Syntax::Glob`] syntax `*` is an atom instead of a quantifier.
+pub(crate) struct Parser<'p> {
+    pattern: &'p str,
+    syntax: Syntax,
+    /// Whether `\d`, `\w` and `\s` match Unicode digits, letters and whitespace
+    unicode: bool,
+    /// Flags of the pattern parsed at the current position
+    flags: Flags,
+    position: usize,
+    group_count: usize,
+    /// Names of the named groups parsed so far
+    group_names: Vec<String>,
+    /// Groups and spans of the backreferences, checked once all the groups are parsed
+    backreferences: Vec<(usize, Range<usize>)>,
+}
+
+impl<'p> Parser<'p> {
+    pub fn new(pattern: &'p str, syntax: Syntax) -> Self {
+        Self {
+            pattern,
+            syntax,
+            unicode: false,
+            flags: Flags::default(),
+            position: 0,
+            group_count: 0,
+            group_names: Vec::new(),
+            backreferences: Vec::new(),
+        }
+    }
+
+    pub fn unicode(mut self, yes: bool) -> Self {
+        self.unicode = yes;
+        self
+    }
+
+    /// Makes the pattern match letters regardless of their ASCII case, same as a leading `(?i)`
+    pub fn case_insensitive(mut self, yes: bool) -> Self {
+        self.flags.case_insensitive = yes;
+        self
+    }
+
+    pub fn parse(mut self) -> ParseResult<PatternList> {
+        let pattern = self.parse_alteration()?;
+        if self.position < self.pattern.len() {
+            // Only an unmatched `)` stops the top level alteration
+            return Err(self.error(
+                "incorrect group pattern: ')' without '('",
+                self.position..self.position + 1,
+            ));
+        }
+        let missing_group = self
+            .backreferences
+            .iter()
+            .find(|(group, _)| *group > self.group_count);
+        if let Some((group, span)) = missing_group {
+            return Err(self.error(
+                format!("incorrect backreference: group {group} does not exist"),
+                span.clone(),
+            ));
+        }
+        if program_size(pattern.items()) > MAX_PROGRAM_SIZE {
+            return Err(self.error(
+                "incorrect pattern: pattern is too large",
+                0..self.pattern.len(),
+            ));
+        }
+        Ok(pattern)
+    }
+
+    fn error(&self, message: impl Into<String>, span: Range<usize>) -> ParseError {
+        ParseError::new(message, self.pattern, span)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.pattern[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.position += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let is_next = self.peek() == Some(expected);
+        if is_next {
+            self.position += expected.len_utf8();
+        }
+        is_next
+    }
+
+    fn parse_alteration(&mut self) -> ParseResult<PatternList> {
+        let mut variants = vec![self.parse_concat()?];
+        while self.eat('|') {
+            variants.push(self.parse_concat()?);
+        }
+        if variants.len() == 1 {
+            return Ok(variants.remove(0));
+        }
+        Ok(PatternList::new(vec![PatternItem::new_text(
+            TextToken::Alteration(variants),
+        )]))
+    }
+
+    fn parse_concat(&mut self) -> ParseResult<PatternList> {
+        let mut items: Vec<PatternItem> = Vec::new();
+        loop {
+            if self.flags.extended {
+                self.skip_whitespace_and_comments();
+            }
+            let Some(ch) = self.peek() else {
+                break;
+            };
+            let start = self.position;
+            match ch {
+                '|' | ')' => break,
+                '*' if self.syntax == Syntax::Glob => {
+                    self.next();
+                    // Glob-like wildcard: any characters, at least one
+                    let mut item = PatternItem::new_char(CharToken::Wildcard);
+                    item.apply_modifier(TokenModifier::OneOrMore, Greediness::Greedy);
+                    items.push(item);
+                    if self.eat('.') {
+                        items.push(PatternItem::new_char(CharToken::Exact('.')));
+                    }
+                }
+                '?' | '*' | '+' | '{' => {
+                    let modifier = self.parse_quantifier()?;
+                    let greediness = if self.eat('?') {
+                        Greediness::Lazy
+                    } else if self.eat('+') {
+                        Greediness::Possessive
+                    } else {
+                        Greediness::Greedy
+                    };
+                    let Some(mut item) = items.pop() else {
+                        return Err(self.error(
+                            format!("incorrect modifier usage: '{ch}' used without token"),
+                            start..start + 1,
+                        ));
+                    };
+                    if item.is_repeated() {
+                        // Stacked quantifiers repeat the whole repetition, `a{2}{3}` is `(a{2}){3}`
+                        let inner = PatternList::new(vec![item]);
+                        item = PatternItem::new_text(TextToken::Alteration(vec![inner]));
+                    }
+                    item.apply_modifier(modifier, greediness);
+                    if program_size(slice::from_ref(&item)) > MAX_PROGRAM_SIZE {
+                        return Err(self.error(
+                            "incorrect quantifiers usage: repetition is too large",
+                            start..self.position,
+                        ));
+                    }
+                    items.push(item);
+                }
+                _ => items.extend(self.parse_atom()?),
+            }
+        }
+        Ok(PatternList::new(items))
+    }
+
+    /// Skips the whitespace and the `#` comments up to the end of line, which the
+    /// extended mode `(?x)` ignores
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            let rest = &self.pattern[self.position..];
+            let trimmed = rest.trim_start();
+            let skipped = match trimmed.strip_prefix('#') {
+                Some(comment) => comment.find('\n').map_or("", |end| &comment[end..]),
+                None => trimmed,
+            };
+            self.position += rest.len() - skipped.len();
+            if skipped.len() == rest.len() {
+                break;
+            }
+        }
+    }
+
+    /// Parses a single token, returns `None` for a flag group like `(?i)`, which only
+    /// changes the flags of the rest of the enclosing group
+    fn parse_atom(&mut self) -> ParseResult<Option<PatternItem>> {
+        let start = self.position;
+        let ch = self
+            .next()
+            .expect("atom is parsed only before the end of pattern");
+        let mut item = match ch {
+            '^' if self.flags.multi_line => PatternItem::new_char(CharToken::StartLine),
+            '^' => PatternItem::new_char(CharToken::StartText),
+            '$' if self.flags.multi_line => PatternItem::new_char(CharToken::EndLine),
+            '$' => PatternItem::new_char(CharToken::EndText),
+            '.' if self.flags.dot_all => PatternItem::new_char(CharToken::Wildcard),
+            '.' => PatternItem::new_char(CharToken::NegativeGroup(CharClass::new([('\n', '\n')]))),
+            '(' => {
+                // Flags set inside the group do not apply after it
+                let outer_flags = self.flags;
+                let Some(kind) = self.parse_group_kind(start)? else {
+                    return Ok(None);
+                };
+                let inner = self.parse_alteration()?;
+                self.flags = outer_flags;
+                if !self.eat(')') {
+                    return Err(self.error(
+                        "incorrect pattern group: group open without end ')'",
+                        start..start + 1,
+                    ));
+                }
+                let token = match kind {
+                    GroupKind::Capture { index, name } => TextToken::Group {
+                        index,
+                        name,
+                        pattern: inner,
+                    },
+                    GroupKind::NonCapturing => TextToken::Alteration(vec![inner]),
+                    GroupKind::Atomic => TextToken::Atomic(inner),
+                    GroupKind::Lookaround { behind, negated } => {
+                        if behind && inner.length_bounds().1.is_none() {
+                            return Err(self.error(
+                                "incorrect pattern group: lookbehind must have a bounded length",
+                                start..self.position,
+                            ));
+                        }
+                        TextToken::Lookaround {
+                            behind,
+                            negated,
+                            pattern: inner,
+                        }
+                    }
+                };
+                // Tokens inside the group are already folded according to their flags
+                return Ok(Some(PatternItem::new_text(token)));
+            }
+            '[' => self.parse_class(start)?,
+            '\\' => self.parse_escape(start)?,
+            _ => PatternItem::new_char(CharToken::Exact(ch)),
+        };
+        if self.flags.case_insensitive {
+            item.token.fold_case(self.unicode);
+        }
+        Ok(Some(item))
+    }
+
+    /// Parses the prefix of a special group like `?:` or `?<name>` after its `(`.
+    /// Capture groups are numbered in the order of their opening parentheses.
+    /// Returns `None` for a flag group like `(?i)`, which has no subpattern.
+    fn parse_group_kind(&mut self, start: usize) -> ParseResult<Option<GroupKind>> {
+        const LOOKAROUNDS: [(&str, bool, bool); 4] = [
+            ("=", false, false),
+            ("!", false, true),
+            ("<=", true, false),
+            ("<!", true, true),
+        ];
+        if !self.eat('?') {
+            self.group_count += 1;
+            return Ok(Some(GroupKind::Capture {
+                index: self.group_count,
+                name: None,
+            }));
+        }
+
+        let rest = &self.pattern[self.position..];
+        if let Some(&(prefix, behind, negated)) = LOOKAROUNDS
+            .iter()
+            .find(|(prefix, ..)| rest.starts_with(prefix))
+        {
+            self.position += prefix.len();
+            if self.syntax != Syntax::Perl {
+                return Err(self.error(
+                    "incorrect pattern group: lookaround is supported only in Perl syntax",
+                    start..self.position,
+                ));
+            }
+            return Ok(Some(GroupKind::Lookaround { behind, negated }));
+        }
+        if let Some(flags) = self.parse_flags(start)? {
+            self.flags = flags;
+            if self.eat(':') {
+                return Ok(Some(GroupKind::NonCapturing));
+            }
+            self.eat(')');
+            return Ok(None);
+        }
+        if self.eat(':') {
+            return Ok(Some(GroupKind::NonCapturing));
+        }
+        if self.eat('>') {
+            return Ok(Some(GroupKind::Atomic));
+        }
+        if self.eat('<') || (self.eat('P') && self.eat('<')) {
+            let name = self.parse_group_name(start)?;
+            self.group_count += 1;
+            return Ok(Some(GroupKind::Capture {
+                index: self.group_count,
+                name: Some(name),
+            }));
+        }
+        self.next();
+        Err(self.error(
+            "incorrect pattern group: unknown group type",
+            start..self.position,
+        ))
+    }
+
+    /// Parses the flags of `(?flags)` or `(?flags:...)` like `i` or `m-s` up to the `)`
+    /// or `:`, where the flags after `-` are turned off. Returns the flags they result in,
+    /// or `None` without consuming anything if the group has no flags.
+    fn parse_flags(&mut self, start: usize) -> ParseResult<Option<Flags>> {
+        let rest = &self.pattern[self.position..];
+        let length = rest
+            .find(|ch| !matches!(ch, 'i' | 'm' | 's' | 'x' | '-'))
+            .unwrap_or(rest.len());
+        if length == 0 || !rest[length..].starts_with([')', ':']) {
+            return Ok(None);
+        }
+
+        let mut flags = self.flags;
+        let mut enabled = true;
+        for ch in rest[..length].chars() {
+            match ch {
+                'i' => flags.case_insensitive = enabled,
+                'm' => flags.multi_line = enabled,
+                's' => flags.dot_all = enabled,
+                'x' => flags.extended = enabled,
+                _ if enabled => enabled = false,
+                _ => {
+                    return Err(self.error(
+                        "incorrect pattern group: repeated '-' in flags",
+                        start..self.position + length + 1,
+                    ))
+                }
+            }
+        }
+        self.position += length;
+        Ok(Some(flags))
+    }
+
+    /// Parses the name of a named group up to its closing `>`
+    fn parse_group_name(&mut self, start: usize) -> ParseResult<String> {
+        let name_start = self.position;
+        let Some(length) = self.pattern[name_start..].find('>') else {
+            return Err(self.error(
+                "incorrect pattern group: group name open without end '>'",
+                start..self.pattern.len(),
+            ));
+        };
+        self.position += length + 1;
+        let name = &self.pattern[name_start..name_start + length];
+
+        let is_valid = name
+            .chars()
+            .next()
+            .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+            && name
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+        if !is_valid {
+            return Err(self.error(
+                format!("incorrect pattern group: invalid group name '{name}'"),
+                start..self.position,
+            ));
+        }
+        if self.group_names.iter().any(|other| other == name) {
+            return Err(self.error(
+                format!("incorrect pattern group: duplicate group name '{name}'"),
+                start..self.position,
+            ));
+        }
+        self.group_names.push(name.to_string());
+        Ok(name.to_string())
+    }
+
+    fn parse_escape(&mut self, start: usize) -> ParseResult<PatternItem> {
+        let Some(ch) = self.next() else {
+            return Err(self.error(
+                "incorrect pattern: \\ symbol without value after it",
+                start..start + 1,
+            ));
+        };
+        if let Some(char_type) = char_type(ch).filter(|_| !self.unicode) {
+            return Ok(PatternItem::new_char_type(char_type));
+        }
+        if let Some(class) = self.parse_class_escape(ch, start)? {
+            return Ok(PatternItem::new_char(CharToken::Group(class)));
+        }
+        let unicode = self.unicode;
+        let item = match ch {
+            'b' => PatternItem::new_char(CharToken::WordBoundary { unicode }),
+            'B' => PatternItem::new_char(CharToken::NotWordBoundary { unicode }),
+            '<' => PatternItem::new_char(CharToken::WordStart { unicode }),
+            '>' => PatternItem::new_char(CharToken::WordEnd { unicode }),
+            'A' => PatternItem::new_char(CharToken::StartText),
+            'z' => PatternItem::new_char(CharToken::EndText),
+            '1'..='9' => {
+                let group = ch as usize - '0' as usize;
+                self.backreferences.push((group, start..self.position));
+                PatternItem::new_text(TextToken::Backreference {
+                    group,
+                    case_insensitive: false,
+                })
+            }
+            _ => PatternItem::new_char(CharToken::Exact(self.parse_char_escape(ch, start)?)),
+        };
+        Ok(item)
+    }
+
+    /// Parses the character of an escape like `\t`, `\x41`, `\u{1F600}`, `\012` or `\.`
+    /// after its escape letter. Only punctuation and space are escaped as themselves,
+    /// an escape of any other character is an error.
+    fn parse_char_escape(&mut self, ch: char, start: usize) -> ParseResult<char> {
+        let escaped = match ch {
+            't' => Some('\t'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            'f' => Some('\x0C'),
+            'v' => Some('\x0B'),
+            'a' => Some('\x07'),
+            'e' => Some('\x1B'),
+            'x' => {
+                let digits = self.take_while(2, |ch| ch.is_ascii_hexdigit());
+                Some(digits)
+                    .filter(|digits| digits.len() == 2)
+                    .and_then(|digits| parse_code_point(digits, 16))
+            }
+            'u' => {
+                let is_open = self.eat('{');
+                let digits = self.take_while(6, |ch| ch.is_ascii_hexdigit());
+                let is_closed = is_open && self.eat('}');
+                Some(digits)
+                    .filter(|digits| is_closed && !digits.is_empty())
+                    .and_then(|digits| parse_code_point(digits, 16))
+            }
+            // `\0` followed by up to two more octal digits
+            '0' => {
+                let digits = self.take_while(2, |ch| ch.is_digit(8));
+                Some(parse_code_point(digits, 8).unwrap_or('\0'))
+            }
+            _ if ch.is_ascii_punctuation() || ch == ' ' => Some(ch),
+            _ => {
+                return Err(self.error(
+                    format!("incorrect pattern: unknown escape \\{ch}"),
+                    start..self.position,
+                ))
+            }
+        };
+        escaped.ok_or_else(|| {
+            let sequence = &self.pattern[start..self.position];
+            self.error(
+                format!("incorrect pattern: invalid escape {sequence}"),
+                start..self.position,
+            )
+        })
+    }
+
+    /// Consumes up to `max` characters while the predicate holds for them
+    fn take_while(&mut self, max: usize, predicate: impl Fn(char) -> bool) -> &'p str {
+        let rest = &self.pattern[self.position..];
+        let length = rest
+            .chars()
+            .take(max)
+            .take_while(|&ch| predicate(ch))
+            .map(char::len_utf8)
+            .sum();
+        self.position += length;
+        &rest[..length]
+    }
+
+    /// Parses the bracket expression after its `[`, where `]` right after the opening
+    /// bracket is a literal
+    fn parse_class(&mut self, start: usize) -> ParseResult<PatternItem> {
+        let negated = self.eat('^');
+        let mut class = CharClass::default();
+        let mut is_first = true;
+        while is_first || !self.eat(']') {
+            is_first = false;
+            let atom_start = self.position;
+            let start_ch = match self.parse_class_atom(start)? {
+                ClassAtom::Class(other) => {
+                    class.union(&other);
+                    continue;
+                }
+                ClassAtom::Char(ch) => ch,
+            };
+
+            // `-` is a literal at the end of the class
+            let rest = &self.pattern[self.position..];
+            if !rest.starts_with('-') || rest.starts_with("-]") {
+                class.push(start_ch, start_ch);
+                continue;
+            }
+
+            self.next();
+            let ClassAtom::Char(end_ch) = self.parse_class_atom(start)? else {
+                return Err(self.error(
+                    "incorrect class range: character class can not bound a range",
+                    atom_start..self.position,
+                ));
+            };
+            if start_ch > end_ch {
+                return Err(self.error(
+                    format!("incorrect class range: {start_ch}-{end_ch} is out of order"),
+                    atom_start..self.position,
+                ));
+            }
+            class.push(start_ch, end_ch);
+        }
+
+        let token = if negated {
+            CharToken::NegativeGroup(class)
+        } else {
+            CharToken::Group(class)
+        };
+        Ok(PatternItem::new_char(token))
+    }
+
+    /// Parses a single character, an escaped character type or a POSIX class like
+    /// `[:alpha:]` of a bracket expression
+    fn parse_class_atom(&mut self, start: usize) -> ParseResult<ClassAtom> {
+        if let Some(class) = self.parse_posix_class()? {
+            return Ok(ClassAtom::Class(class));
+        }
+        let unclosed = |parser: &Self| {
+            parser.error(
+                "incorrect pattern group: group open without end ']'",
+                start..start + 1,
+            )
+        };
+        let escape_start = self.position;
+        match self.next() {
+            None => Err(unclosed(self)),
+            Some('\\') => {
+                let Some(ch) = self.next() else {
+                    return Err(unclosed(self));
+                };
+                if let Some(class) = self.parse_class_escape(ch, escape_start)? {
+                    return Ok(ClassAtom::Class(class));
+                }
+                Ok(ClassAtom::Char(self.parse_char_escape(ch, escape_start)?))
+            }
+            Some(ch) => Ok(ClassAtom::Char(ch)),
+        }
+    }
+
+    /// Parses the class of an escape like `\d` or `\p{Greek}` after its escape letter,
+    /// returns `None` for the escapes of other tokens
+    fn parse_class_escape(&mut self, ch: char, start: usize) -> ParseResult<Option<CharClass>> {
+        if let Some(char_type) = char_type(ch) {
+            let class = self
+                .unicode
+                .then(|| unicode::char_type_class(&char_type))
+                .flatten();
+            return Ok(Some(class.unwrap_or_else(|| char_type.class())));
+        }
+        if ch != 'p' && ch != 'P' {
+            return Ok(None);
+        }
+
+        let name = if self.eat('{') {
+            let rest = &self.pattern[self.position..];
+            let Some(length) = rest.find('}') else {
+                return Err(self.error(
+                    "incorrect pattern: Unicode property open without end '}'",
+                    start..self.pattern.len(),
+                ));
+            };
+            self.position += length + 1;
+            &rest[..length]
+        } else {
+            // Single letter general category like `\pL`
+            let name_start = self.position;
+            self.next();
+            &self.pattern[name_start..self.position]
+        };
+        let Some(class) = unicode::property_class(name) else {
+            return Err(self.error(
+                format!("incorrect pattern: unknown Unicode property '{name}'"),
+                start..self.position,
+            ));
+        };
+        Ok(Some(if ch == 'P' { class.negate() } else { class }))
+    }
+
+    /// Parses `[:name:]` or the negated `[:^name:]`, a `[` without the closing `:]` is
+    /// a literal
+    fn parse_posix_class(&mut self) -> ParseResult<Option<CharClass>> {
+        let start = self.position;
+        let Some(rest) = self.pattern[start..].strip_prefix("[:") else {
+            return Ok(None);
+        };
+        let Some(length) = rest.find(":]") else {
+            return Ok(None);
+        };
+        self.position += length + 4;
+
+        let (negated, name) = match rest[..length].strip_prefix('^') {
+            Some(name) => (true, name),
+            None => (false, &rest[..length]),
+        };
+        let Some(char_type) = CharType::from_posix_name(name) else {
+            return Err(self.error(
+                format!("incorrect class: unknown POSIX class [:{name}:]"),
+                start..self.position,
+            ));
+        };
+        let class = char_type.class();
+        Ok(Some(if negated { class.negate() } else { class }))
+    }
+
+    fn parse_quantifier(&mut self) -> ParseResult<TokenModifier> {
+        let start = self.position;
+        match self.next() {
+            Some('?') => return Ok(TokenModifier::Optional),
+            Some('*') => return Ok(TokenModifier::ZeroOrMore),
+            Some('+') => return Ok(TokenModifier::OneOrMore),
+            _ => {}
+        }
+
+        let Some(length) = self.pattern[self.position..].find('}') else {
+            return Err(self.error(
+                "incorrect pattern group: group open without end '}'",
+                start..start + 1,
+            ));
+        };
+        let body = &self.pattern[self.position..self.position + length];
+        self.position += length + 1;
+        let span = start..self.position;
+
+        let parse_count = |count: &str| {
+            count.parse::<usize>().map_err(|_| {
+                self.error(
+                    format!("incorrect quantifiers usage: '{count}' is not a number"),
+                    span.clone(),
+                )
+            })
+        };
+        let modifier = match body.split_once(',') {
+            None => TokenModifier::Exact(parse_count(body)?),
+            Some((min, "")) => TokenModifier::AtLeast(parse_count(min)?),
+            Some((min, max)) => {
+                let (min, max) = (parse_count(min)?, parse_count(max)?);
+                if min > max {
+                    return Err(self.error(
+                        format!("incorrect quantifiers usage: {{{body}}} minimum is above maximum"),
+                        span,
+                    ));
+                }
+                TokenModifier::Between(min, max)
+            }
+        };
+        Ok(modifier)
+    }
+}
+
+/// Character of the code point written with the digits in the radix, `None` if there
+/// is no such character
+fn parse_code_point(digits: &str, radix: u32) -> Option<char> {
+    u32::from_str_radix(digits, radix)
+        .ok()
+        .and_then(char::from_u32)
+}
+
+/// Flags changing the meaning of the pattern parts that follow them, set with `(?imsx)`
+#[derive(Clone, Copy, Debug, Default)]
+struct Flags {
+    /// `i`: letters match regardless of their ASCII case
+    case_insensitive: bool,
+    /// `m`: `^` and `$` match at the start and end of every line, not only of the input
+    multi_line: bool,
+    /// `s`: `.` matches `\n` too
+    dot_all: bool,
+    /// `x`: whitespace and `#` comments up to the end of line are ignored
+    extended: bool,
+}
+
+/// Kind of a parenthesized group, told by its prefix
+enum GroupKind {
+    Capture { index: usize, name: Option<String> },
+    NonCapturing,
+    Atomic,
+    Lookaround { behind: bool, negated: bool },
+}
+
+/// Part of a bracket expression
+enum ClassAtom {
+    Char(char),
+    Class(CharClass),
+}
+
+/// Character type of the escape letter, like `d` of `\d`
+fn char_type(ch: char) -> Option<CharType> {
+    let char_type = match ch {
+        'd' => CharType::Digit,
+        'D' => CharType::NonDigit,
+        'w' => CharType::Alphanumeric,
+        'W' => CharType::NonAlphanumeric,
+        's' => CharType::Whitespace,
+        'S' => CharType::NonWhitespace,
+        _ => return None,
+    };
+    Some(char_type)
+}
@@ -1,114 +1,121 @@
-use std::str::{Bytes, FromStr};
-
-use crate::{pattern_item::PatternItem, pattern_list::PatternList, token::CharToken};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::backtrack::Backtracker;
+use crate::builder::PatternBuilder;
+use crate::captures::Captures;
+use crate::dfa::LazyDfa;
+use crate::haystack::Haystack;
+use crate::matches::{CaptureMatches, FindMatches, Match};
+use crate::nfa::Program;
+use crate::pattern_list::PatternList;
+use crate::pikevm::PikeVm;
 
 pub struct Pattern {
-    pattern: PatternList,
+    group_count: usize,
+    /// Names of the capture groups, `None` for the unnamed ones
+    group_names: Arc<[Option<String>]>,
+    program: Program,
+    /// Missing if the program can only be run by backtracking or uses assertions the
+    /// DFA can not track
+    dfa: Option<LazyDfa>,
 }
 
 impl Pattern {
     pub fn new(pattern_list: PatternList) -> Self {
+        let group_count = pattern_list.group_count();
+        let group_names = pattern_list.group_names().into();
+        let program = Program::compile(&pattern_list, group_count);
+        let dfa = match program.needs_backtracking {
+            true => None,
+            false => LazyDfa::new(&program),
+        };
         Self {
-            pattern: pattern_list,
+            group_count,
+            group_names,
+            program,
+            dfa,
         }
     }
 
     pub fn match_line(&mut self, input: &str) -> anyhow::Result<bool> {
-        let res = self.match_line_proceed(input);
-        self.pattern.reset();
-        res
+        Ok(self.is_match(Haystack::from_str(input)))
     }
 
-    fn match_line_proceed(&mut self, input: &str) -> anyhow::Result<bool> {
-        let mut input = input.bytes();
+    /// Same as [`Pattern::match_line`] for input which may not be valid UTF-8,
+    /// every byte of such input is matched as a separate character
+    pub fn match_line_bytes(&mut self, input: &[u8]) -> anyhow::Result<bool> {
+        Ok(self.is_match(Haystack::new(input)))
+    }
 
-        if self.pattern.is_next_token(CharToken::StartLine) {
-            self.pattern.next();
-            return Ok(self.match_here(&mut input));
-        }
+    /// Finds the leftmost match of the pattern in the input
+    pub fn find(&mut self, input: &str) -> Option<Match> {
+        self.find_at(Haystack::from_str(input), 0)
+    }
 
-        loop {
-            // Clone because we can start pattern from the start if it failed at some point 
-            // without changing the input
-            if self.match_here(&mut input.clone()) {
-                return Ok(true);
-            }
-            if input.next().is_none() {
-                return Ok(false);
-            }
-            self.pattern.reset();
-        }
+    /// Same as [`Pattern::find`] for input which may not be valid UTF-8, matched byte by
+    /// byte like Latin-1 if it is not
+    pub fn find_bytes(&mut self, input: &[u8]) -> Option<Match> {
+        self.find_at(Haystack::new(input), 0)
     }
 
-    pub fn match_here(&mut self, input: &mut Bytes) -> bool {
-        if self.pattern.is_next_optional() && input.len() == 0 {
-            return true;
-        }
-        dbg!(&input, self.pattern.clone());
-        if self.pattern.is_next_token(CharToken::EndLine) && input.len() == 0 {
-            return true;
-        }
-        let Some(pattern_item) = self.pattern.next() else {
-            return true;
-        };
-        let pattern_item = pattern_item.clone();
+    /// Iterates over all non-overlapping matches of the pattern in the input
+    pub fn find_iter<'p, 'h>(&'p mut self, input: &'h str) -> FindMatches<'p, 'h> {
+        FindMatches::new(self, Haystack::from_str(input))
+    }
 
-        let Some(skip_count) = self.handle_match_option(pattern_item, &mut input.clone()) else {
-            return false;
-        };
+    /// Same as [`Pattern::find_iter`] for input which may not be valid UTF-8, matched byte
+    /// by byte like Latin-1 if it is not
+    pub fn find_iter_bytes<'p, 'h>(&'p mut self, input: &'h [u8]) -> FindMatches<'p, 'h> {
+        FindMatches::new(self, Haystack::new(input))
+    }
 
-        for _ in 0..skip_count {
-            input.next();
-        }
-        self.match_here(input)
+    /// Finds the leftmost match along with the spans of all capture groups
+    pub fn captures(&mut self, input: &str) -> Option<Captures> {
+        self.captures_at(Haystack::from_str(input), 0)
     }
 
-    fn handle_match_option(&self, pattern_item: PatternItem, input: &mut Bytes) -> Option<usize> {
-        let match_option = pattern_item.match_input(input);
-        let mut skip_count = match_option?;
+    /// Same as [`Pattern::captures`] for input which may not be valid UTF-8, matched byte
+    /// by byte like Latin-1 if it is not
+    pub fn captures_bytes(&mut self, input: &[u8]) -> Option<Captures> {
+        self.captures_at(Haystack::new(input), 0)
+    }
 
-        if skip_count == 0 {
-            return Some(0);
-        }
+    /// Iterates over the captures of all non-overlapping matches of the pattern
+    pub fn captures_iter<'p, 'h>(&'p mut self, input: &'h str) -> CaptureMatches<'p, 'h> {
+        CaptureMatches::new(self, Haystack::from_str(input))
+    }
 
-        if pattern_item.is_multiple_match() {
-            let res = self.match_more(input, pattern_item);
-            match res {
-                None => return None,
-                Some(count) => skip_count += count,
-            }
-        }
+    /// Same as [`Pattern::captures_iter`] for input which may not be valid UTF-8, matched
+    /// byte by byte like Latin-1 if it is not
+    pub fn captures_iter_bytes<'p, 'h>(&'p mut self, input: &'h [u8]) -> CaptureMatches<'p, 'h> {
+        CaptureMatches::new(self, Haystack::new(input))
+    }
 
-        Some(skip_count)
-    }
-
-    fn match_more(&self, input: &mut Bytes, pattern_item: PatternItem) -> Option<usize> {
-        let mut skip_count = 0;
-        let mut match_times = 1;
-        while pattern_item.can_match_more(match_times) {
-            if input.len() == 0 {
-                break;
-            };
-            let Some(match_count) = pattern_item.match_input(input) else {
-                break;
-            };
-            skip_count += match_count;
-            match_times += 1;
-
-            if pattern_item.is_least_matched(match_times) {
-                if let Some(next_pattern_item) = self.pattern.peek() {
-                    if next_pattern_item.match_input(&mut input.clone()).is_some() {
-                        break;
-                    }
-                }
-            }
-        }
+    /// Number of capture groups in the pattern, not counting the whole match
+    pub fn group_count(&self) -> usize {
+        self.group_count
+    }
 
-        if pattern_item.is_least_matched(match_times) {
-            Some(skip_count)
-        } else {
-            None
+    fn is_match(&mut self, haystack: Haystack) -> bool {
+        if let Some(dfa) = &mut self.dfa {
+            return dfa.is_match(&self.program, haystack);
         }
+        self.find_at(haystack, 0).is_some()
+    }
+
+    /// Finds the leftmost match starting at or after the `start` byte offset.
+    /// Anchors still refer to the whole input.
+    pub(crate) fn find_at(&mut self, haystack: Haystack, start: usize) -> Option<Match> {
+        self.captures_at(haystack, start)?.get(0)
+    }
+
+    pub(crate) fn captures_at(&mut self, haystack: Haystack, start: usize) -> Option<Captures> {
+        let captures = match self.program.needs_backtracking {
+            true => Backtracker::new(&self.program, haystack, self.group_count).captures_at(start),
+            false => PikeVm::new(&self.program).captures_at(haystack, start),
+        };
+        Some(captures?.with_names(self.group_names.clone()))
     }
 }
 
@@ -116,8 +123,6 @@ impl FromStr for Pattern {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {
-            pattern: PatternList::from_str(s)?,
-        })
+        PatternBuilder::new(s).build()
     }
 }
@@ -1,24 +1,23 @@
-use std::str::Bytes;
-
 use crate::text_token::TextToken;
 use crate::token::{CharToken, CharType, Token};
-use crate::token_modifier::TokenModifier;
+use crate::token_modifier::{Greediness, TokenModifier};
 
+/// Token repeated between `min` and `max` times, without upper bound if `max` is `None`
 #[derive(Clone, Debug)]
 pub struct PatternItem {
     pub(crate) token: Token,
-    pub optional: bool,
-    more_than: Option<usize>,
-    less_than: Option<usize>,
+    pub(crate) min: usize,
+    pub(crate) max: Option<usize>,
+    pub(crate) greediness: Greediness,
 }
 
 impl PatternItem {
     pub(crate) fn new(token: Token) -> Self {
         Self {
             token,
-            optional: false,
-            more_than: None,
-            less_than: None,
+            min: 1,
+            max: Some(1),
+            greediness: Greediness::Greedy,
         }
     }
 
@@ -34,49 +33,35 @@ impl PatternItem {
         Self::new(Token::Char(CharToken::CharType(char)))
     }
 
-    pub fn apply_modifier(&mut self, modifier: TokenModifier) {
+    pub fn apply_modifier(&mut self, modifier: TokenModifier, greediness: Greediness) {
+        self.greediness = greediness;
         match modifier {
-            TokenModifier::Optional => self.optional = true,
-            TokenModifier::OneOrMore => self.more_than = Some(1),
+            TokenModifier::Optional => self.min = 0,
+            TokenModifier::ZeroOrMore => {
+                self.min = 0;
+                self.max = None;
+            }
+            TokenModifier::OneOrMore => {
+                self.min = 1;
+                self.max = None;
+            }
             TokenModifier::Exact(exact) => {
-                self.more_than = Some(exact);
-                self.less_than = Some(exact);
+                self.min = exact;
+                self.max = Some(exact);
             }
-            TokenModifier::AtLeast(at_least) => self.more_than = Some(at_least),
-            TokenModifier::Between(more_than, less_than) => {
-                self.more_than = Some(more_than);
-                self.less_than = Some(less_than);
+            TokenModifier::AtLeast(at_least) => {
+                self.min = at_least;
+                self.max = None;
             }
-        }
-    }
-
-    pub fn match_input(&self, input: &mut Bytes) -> Option<usize> {
-        match self.token.match_input(input) {
-            Some(count) => Some(count),
-            None => {
-                if self.optional {
-                    Some(0)
-                } else {
-                    None
-                }
+            TokenModifier::Between(min, max) => {
+                self.min = min;
+                self.max = Some(max);
             }
         }
     }
 
-    pub fn is_multiple_match(&self) -> bool {
-        self.more_than.is_some() || self.less_than.is_some()
-    }
-
-    pub fn is_least_matched(&self, current_times: usize) -> bool {
-        let more_than = self.more_than.unwrap_or(1);
-        current_times >= more_than
-    }
-
-    pub fn can_match_more(&self, current_times: usize) -> bool {
-        if let Some(less_than) = self.less_than {
-            current_times < less_than
-        } else {
-            true
-        }
+    /// Whether a modifier was applied, so the token is not matched exactly once
+    pub fn is_repeated(&self) -> bool {
+        self.min != 1 || self.max != Some(1)
     }
 }
@@ -1,175 +1,142 @@
+use crate::parser::Parser;
 use crate::pattern_item::PatternItem;
+use crate::syntax::Syntax;
 use crate::text_token::TextToken;
-use crate::token::{CharToken, CharType, Token};
-use crate::token_modifier::TokenModifier;
-use anyhow::Context;
-use anyhow::{anyhow, bail};
-use std::ops::Index;
-use std::{
-    iter::{Enumerate, Peekable},
-    str::{Bytes, FromStr},
-};
+use crate::token::{CharToken, Token};
+use std::str::FromStr;
 
+/// Sequence of pattern items matched one after another
 #[derive(Clone, Debug)]
 pub struct PatternList {
     inner: Vec<PatternItem>,
-    cursor: usize,
 }
 
 impl PatternList {
-    pub fn is_next_token(&self, token_to_check: CharToken) -> bool {
-        let t = self.inner.get(self.cursor);
-        match t {
-            Some(PatternItem {
-                token: Token::Char(token),
-                ..
-            }) => *token == token_to_check,
-            _ => false,
-        }
+    pub(crate) fn new(inner: Vec<PatternItem>) -> Self {
+        Self { inner }
     }
 
-    pub fn is_next_optional(&self) -> bool {
-        let Some(pattern_item) = self.inner.get(self.cursor) else {
-            return false;
-        };
-        pattern_item.optional
+    pub fn items(&self) -> &[PatternItem] {
+        &self.inner
     }
 
-    pub fn next(&mut self) -> Option<&PatternItem> {
-        let val = self.inner.get(self.cursor);
-        self.cursor += 1;
-        val
+    /// Whether the pattern can only match at the start of the input
+    pub fn is_start_anchored(&self) -> bool {
+        matches!(
+            self.inner.first(),
+            Some(PatternItem {
+                token: Token::Char(CharToken::StartText),
+                ..
+            })
+        )
     }
 
-    pub fn peek(&self) -> Option<&PatternItem> {
-        self.inner.get(self.cursor)
+    /// Makes every token of the pattern match letters regardless of their case, only the
+    /// ASCII letters unless `unicode` is set
+    pub fn fold_case(&mut self, unicode: bool) {
+        self.inner
+            .iter_mut()
+            .for_each(|item| item.token.fold_case(unicode));
     }
 
-    pub fn reset(&mut self) {
-        self.cursor = 0;
+    /// Highest capture group index defined in the pattern
+    pub fn group_count(&self) -> usize {
+        let mut group_count = 0;
+        self.visit_text_tokens(&mut |token| {
+            if let TextToken::Group { index, .. } = token {
+                group_count = group_count.max(*index);
+            }
+        });
+        group_count
     }
-}
 
-impl FromStr for PatternList {
-    type Err = anyhow::Error;
-
-    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
-        let mut inner: Vec<PatternItem> = Vec::new();
-
-        let length = pattern.len();
-        let mut pattern = pattern.bytes().enumerate().peekable();
+    /// Names of the capture groups indexed by the group, `None` for the unnamed ones
+    /// and the whole match group 0
+    pub fn group_names(&self) -> Vec<Option<String>> {
+        let mut names = vec![None; self.group_count() + 1];
+        self.visit_text_tokens(&mut |token| {
+            if let TextToken::Group { index, name, .. } = token {
+                names[*index].clone_from(name);
+            }
+        });
+        names
+    }
 
-        while let Some((i, char)) = pattern.next() {
-            if char == b'?' {
-                let Some(item) = inner.last_mut() else {
-                    bail!("incorrect modifier usage: '?' used without token")
-                };
-                item.apply_modifier(TokenModifier::Optional);
-            } else if char == b'+' {
-                let Some(item) = inner.last_mut() else {
-                    bail!("incorrect modifier usage: '+' used without token")
-                };
-                item.apply_modifier(TokenModifier::OneOrMore);
-            } else if char == b'^' && i == 0 {
-                inner.push(PatternItem::new_char(CharToken::StartLine))
-            } else if char == b'$' && i == length - 1 {
-                inner.push(PatternItem::new_char(CharToken::EndLine))
-            } else if char == b'.' {
-                inner.push(PatternItem::new_char(CharToken::Wildcard))
-            } else if char == b'*' {
-                let mut item = PatternItem::new_char(CharToken::Wildcard);
-                item.apply_modifier(TokenModifier::OneOrMore);
-                inner.push(item);
-                if let Some((_, b'.')) = pattern.peek() {
-                    inner.push(PatternItem::new_char(CharToken::Exact(b'.')));
-                    pattern.next();
-                }
-            } else if char == b'\\' {
-                let var_name = anyhow!("incorrect pattern: \\ symbol without value after it");
-                let (_, next_char) = pattern.next().ok_or(var_name)?;
-                match next_char {
-                    b'd' => inner.push(PatternItem::new_char_type(CharType::Digit)),
-                    b'D' => inner.push(PatternItem::new_char_type(CharType::NonDigit)),
-                    b'w' => inner.push(PatternItem::new_char_type(CharType::Alphanumeric)),
-                    b'W' => inner.push(PatternItem::new_char_type(CharType::NonAlphanumeric)),
-                    b's' => inner.push(PatternItem::new_char_type(CharType::Whitespace)),
-                    b'S' => inner.push(PatternItem::new_char_type(CharType::NonWhitespace)),
-                    _ => inner.push(PatternItem::new_char(CharToken::Exact(next_char))),
-                }
-            } else if char == b'(' {
-                let group = parse_group(&mut pattern, b')')?;
-                let alterations = group
-                    .split(|&c| c == b'|')
-                    .flat_map(|v| {
-                        let str = String::from_utf8(v.to_vec())?;
-                        PatternList::from_str(&str)
-                    })
-                    .collect::<Vec<_>>();
-                inner.push(PatternItem::new_text(TextToken::Alteration(alterations)));
-            } else if char == b'[' {
-                let mut group = parse_group(&mut pattern, b']')?;
+    /// Highest capture group index referenced by a backreference
+    pub fn max_backreference(&self) -> Option<usize> {
+        let mut max_backreference = None;
+        self.visit_text_tokens(&mut |token| {
+            if let TextToken::Backreference { group, .. } = token {
+                max_backreference = max_backreference.max(Some(*group));
+            }
+        });
+        max_backreference
+    }
 
-                if group[0] == b'^' {
-                    group.remove(0);
-                    inner.push(PatternItem::new_char(CharToken::NegativeGroup(group)))
-                } else {
-                    inner.push(PatternItem::new_char(CharToken::Group(group)))
-                }
-            } else if char == b'{' {
-                let group = parse_group(&mut pattern, b'}')?;
-                let group = String::from_utf8(group).context("parse quantifiers group to UTF-8")?;
-                let modifier = if let Some((more_than, less_than)) = group.split_once(',') {
-                    let at_least = more_than
-                        .parse::<usize>()
-                        .context("quantifiers 'at least' value parse")?;
-                    if less_than.is_empty() {
-                        TokenModifier::AtLeast(at_least)
-                    } else {
-                        let less_than = less_than
-                            .parse::<usize>()
-                            .context("quantifiers 'at least' value parse")?;
-                        TokenModifier::Between(at_least, less_than)
-                    }
-                } else {
-                    let exact = group
-                        .parse::<usize>()
-                        .context("quantifiers exact value parse")?;
-                    TokenModifier::Exact(exact)
-                };
+    /// Fewest and most characters a match of the pattern spans, the most is `None` if
+    /// there is no limit
+    pub fn length_bounds(&self) -> (usize, Option<usize>) {
+        self.inner.iter().map(item_length_bounds).fold(
+            (0, Some(0)),
+            |(min, max), (item_min, item_max)| {
+                let max = max
+                    .zip(item_max)
+                    .and_then(|(max, item_max)| max.checked_add(item_max));
+                (min.saturating_add(item_min), max)
+            },
+        )
+    }
 
-                if let Some(item) = inner.last_mut() {
-                    item.apply_modifier(modifier);
-                } else {
-                    bail!("incorrect quantifiers usage: used without token");
-                }
-            } else {
-                inner.push(PatternItem::new_char(CharToken::Exact(char)))
+    /// Calls `f` for every text token, including the ones nested in other tokens
+    fn visit_text_tokens(&self, f: &mut impl FnMut(&TextToken)) {
+        for item in &self.inner {
+            if let Token::Text(token) = &item.token {
+                f(token);
+                token
+                    .subpatterns()
+                    .iter()
+                    .for_each(|pattern| pattern.visit_text_tokens(f));
             }
         }
-
-        Ok(Self { inner, cursor: 0 })
     }
 }
 
-impl Index<usize> for PatternList {
-    type Output = PatternItem;
+fn item_length_bounds(item: &PatternItem) -> (usize, Option<usize>) {
+    let (min, max) = token_length_bounds(&item.token);
+    let max = match (max, item.max) {
+        (Some(0), _) => Some(0),
+        (Some(max), Some(times)) => max.checked_mul(times),
+        _ => None,
+    };
+    (min.saturating_mul(item.min), max)
+}
 
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.inner[index]
+/// Shortest and longest text the token matches once, without a longest one if the
+/// token can match text of any length
+pub(crate) fn token_length_bounds(token: &Token) -> (usize, Option<usize>) {
+    match token {
+        Token::Char(token) if token.is_assertion() => (0, Some(0)),
+        Token::Char(_) => (1, Some(1)),
+        Token::Text(TextToken::Group { pattern, .. } | TextToken::Atomic(pattern)) => {
+            pattern.length_bounds()
+        }
+        Token::Text(TextToken::Alteration(variants)) => {
+            let bounds = variants.iter().map(PatternList::length_bounds);
+            let min = bounds.clone().map(|(min, _)| min).min().unwrap_or(0);
+            let max = bounds
+                .map(|(_, max)| max)
+                .try_fold(0, |most, max| max.map(|max| most.max(max)));
+            (min, max)
+        }
+        Token::Text(TextToken::Backreference { .. }) => (0, None),
+        Token::Text(TextToken::Lookaround { .. }) => (0, Some(0)),
     }
 }
 
-fn parse_group(pattern: &mut Peekable<Enumerate<Bytes>>, end_char: u8) -> anyhow::Result<Vec<u8>> {
-    let mut group = Vec::new();
-    let (_, mut char) = pattern.next().ok_or(anyhow!(
-        "incorrect pattern group: group open without end {end_char}"
-    ))?;
-    while char != end_char {
-        group.push(char);
-        char = pattern.next().ok_or(anyhow!("incorrect group pattern"))?.1;
-    }
-    if group.is_empty() {
-        bail!("incorrect group pattern: empty group {end_char}")
+impl FromStr for PatternList {
+    type Err = anyhow::Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Ok(Parser::new(pattern, Syntax::default()).parse()?)
     }
-    Ok(group)
 }
@@ -0,0 +1,143 @@
+use crate::captures::Captures;
+use crate::haystack::Haystack;
+use crate::matches::Match;
+use crate::nfa::{check_assertion, Inst, Program};
+
+/// Simulates all NFA threads in lockstep, so the time is linear in the input length.
+/// Threads are kept in priority order, which gives the same leftmost-first matches
+/// as the backtracking matcher.
+pub(crate) struct PikeVm<'p> {
+    program: &'p Program,
+}
+
+/// Thread list of a single step, each instruction is present at most once
+struct Threads {
+    pcs: Vec<usize>,
+    slots: Vec<Vec<Option<usize>>>,
+    present: Vec<bool>,
+}
+
+impl Threads {
+    fn new(size: usize) -> Self {
+        Self {
+            pcs: Vec::new(),
+            slots: Vec::new(),
+            present: vec![false; size],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.pcs.clear();
+        self.slots.clear();
+        self.present.fill(false);
+    }
+}
+
+impl<'p> PikeVm<'p> {
+    pub fn new(program: &'p Program) -> Self {
+        Self { program }
+    }
+
+    /// Finds the leftmost match starting at or after the `start` byte offset
+    pub fn captures_at(&self, haystack: Haystack, start: usize) -> Option<Captures> {
+        let size = self.program.insts.len();
+        let mut current = Threads::new(size);
+        let mut next = Threads::new(size);
+        let mut matched: Option<Vec<Option<usize>>> = None;
+
+        let mut position = start;
+        loop {
+            // New match attempts are started until the leftmost match is found
+            if matched.is_none() {
+                let slots = vec![None; self.program.slot_count];
+                self.add_thread(&mut current, 0, haystack, position, slots);
+            }
+            // Without threads a later attempt can still match, unless the first one
+            // was the only possible
+            if current.pcs.is_empty() && (matched.is_some() || self.program.is_start_anchored) {
+                break;
+            }
+
+            let next_char = haystack.char_at(position);
+            for i in 0..current.pcs.len() {
+                match (&self.program.insts[current.pcs[i]], next_char) {
+                    (Inst::Match, _) => {
+                        matched = Some(current.slots[i].clone());
+                        // Lower priority threads can not change the match
+                        break;
+                    }
+                    (Inst::Char(token), Some((ch, len))) if token.match_char(ch) => {
+                        let slots = current.slots[i].clone();
+                        self.add_thread(
+                            &mut next,
+                            current.pcs[i] + 1,
+                            haystack,
+                            position + len,
+                            slots,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+            let Some((_, len)) = next_char else {
+                break;
+            };
+            position += len;
+        }
+
+        let slots = matched?;
+        let mut captures = Captures::new(slots.len() / 2 - 1);
+        for (group, span) in slots.chunks(2).enumerate() {
+            if let [Some(start), Some(end)] = span {
+                captures.set(group, Some(Match::new(*start, *end)));
+            }
+        }
+        Some(captures)
+    }
+
+    /// Adds the thread and follows its instructions that do not consume input, keeping
+    /// the threads still to follow on a stack like the DFA closure does
+    fn add_thread(
+        &self,
+        threads: &mut Threads,
+        pc: usize,
+        haystack: Haystack,
+        position: usize,
+        slots: Vec<Option<usize>>,
+    ) {
+        let mut stack = vec![(pc, slots)];
+        while let Some((pc, mut slots)) = stack.pop() {
+            if std::mem::replace(&mut threads.present[pc], true) {
+                continue;
+            }
+            match &self.program.insts[pc] {
+                Inst::Jump(to) => stack.push((*to, slots)),
+                Inst::Split(first, second) => {
+                    // The first target is followed first, so it is pushed last
+                    stack.push((*second, slots.clone()));
+                    stack.push((*first, slots));
+                }
+                Inst::Save(slot) => {
+                    slots[*slot] = Some(position);
+                    stack.push((pc + 1, slots));
+                }
+                Inst::Assert(token) => {
+                    if check_assertion(token, haystack, position) {
+                        stack.push((pc + 1, slots));
+                    }
+                }
+                Inst::Char(_) | Inst::Match => {
+                    threads.pcs.push(pc);
+                    threads.slots.push(slots);
+                }
+                _ => unreachable!(
+                    "{:?} is run only by the backtracker",
+                    self.program.insts[pc]
+                ),
+            }
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+use crate::Pattern;
+
+/// What is printed for every searched input
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Report {
+    /// The selected lines themselves
+    #[default]
+    Lines,
+    /// Number of the selected lines
+    Count,
+    /// Input name if it has any selected line
+    FilesWithMatches,
+    /// Input name if it has no selected lines
+    FilesWithoutMatch,
+}
+
+/// Options changing which lines are selected and how they are printed
+#[derive(Debug, Default)]
+pub(crate) struct SearchOptions {
+    /// Print only the matched parts of the lines, one per output line
+    pub only_matching: bool,
+    /// Select the lines not matched by the pattern
+    pub invert_match: bool,
+    pub report: Report,
+    /// Prefix the printed lines and counts with the input name
+    pub with_filename: bool,
+    /// Number of lines printed after every selected line
+    pub after_context: usize,
+    /// Number of lines printed before every selected line
+    pub before_context: usize,
+    /// Template every match is replaced with in the printed lines, see [`crate::Captures::expand`]
+    pub replace: Option<String>,
+}
+
+/// Runs the pattern over every line of an input and prints the results
+pub(crate) struct Searcher {
+    pattern: Pattern,
+    options: SearchOptions,
+    context: Context,
+}
+
+/// Tracks context lines around the selected ones
+#[derive(Default)]
+struct Context {
+    /// Not printed lines preceding the current one, with their numbers
+    before: VecDeque<(usize, Vec<u8>)>,
+    /// Number of lines still to print after the last selected line
+    after_left: usize,
+    /// Number of the last printed line in the current input
+    last_printed: Option<usize>,
+    /// Whether any line was printed from the previous inputs
+    printed_before: bool,
+}
+
+impl Searcher {
+    pub fn new(pattern: Pattern, options: SearchOptions) -> Self {
+        Self {
+            pattern,
+            options,
+            context: Context::default(),
+        }
+    }
+
+    /// Prints the report of the selected lines of the input named `name`.
+    /// Returns whether the input counts as a match for the exit code.
+    pub fn search(
+        &mut self,
+        mut input: impl BufRead,
+        name: &str,
+        output: &mut impl Write,
+    ) -> anyhow::Result<bool> {
+        let report = self.options.report;
+        self.start_input();
+        let mut count = 0;
+        let mut line_number = 0;
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            if input.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            line_number += 1;
+
+            let line = trim_line_end(&buf);
+            let selected = self.pattern.match_line_bytes(line)? != self.options.invert_match;
+            if !selected {
+                if report == Report::Lines {
+                    self.handle_context_line(line_number, &buf, name, output)?;
+                }
+                continue;
+            }
+            count += 1;
+
+            match report {
+                Report::Lines => {
+                    self.print_before_context(name, output)?;
+                    self.write_separator(line_number, output)?;
+                    self.print_line(line, &buf, name, output)?;
+                    self.context.after_left = self.options.after_context;
+                }
+                Report::Count => {}
+                // The first selected line decides the result, no need to read further
+                Report::FilesWithMatches | Report::FilesWithoutMatch => break,
+            }
+        }
+
+        match report {
+            Report::Lines => {}
+            Report::Count => {
+                write_prefix(output, self.prefix(name), ':')?;
+                writeln!(output, "{count}")?;
+            }
+            Report::FilesWithMatches if count > 0 => writeln!(output, "{name}")?,
+            Report::FilesWithoutMatch if count == 0 => writeln!(output, "{name}")?,
+            Report::FilesWithMatches | Report::FilesWithoutMatch => {}
+        }
+
+        if report == Report::FilesWithoutMatch {
+            Ok(count == 0)
+        } else {
+            Ok(count > 0)
+        }
+    }
+
+    fn print_line(
+        &mut self,
+        line: &[u8],
+        raw_line: &[u8],
+        name: &str,
+        output: &mut impl Write,
+    ) -> io::Result<()> {
+        let prefix = self.prefix(name);
+        if !self.options.only_matching {
+            write_prefix(output, prefix, ':')?;
+            match &self.options.replace {
+                Some(template) => {
+                    let mut replaced = Vec::with_capacity(line.len());
+                    let mut last_end = 0;
+                    for captures in self.pattern.captures_iter_bytes(line) {
+                        let found = captures.get(0).expect("group 0 is the whole match");
+                        replaced.extend_from_slice(&line[last_end..found.start()]);
+                        captures.expand(template, line, &mut replaced);
+                        last_end = found.end();
+                    }
+                    replaced.extend_from_slice(&line[last_end..]);
+                    // Keeps the original line terminator
+                    replaced.extend_from_slice(&raw_line[line.len()..]);
+                    write_raw_line(output, &replaced)?;
+                }
+                None => write_raw_line(output, raw_line)?,
+            }
+        } else if !self.options.invert_match {
+            for captures in self.pattern.captures_iter_bytes(line) {
+                let found = captures.get(0).expect("group 0 is the whole match");
+                if found.is_empty() {
+                    continue;
+                }
+                write_prefix(output, prefix, ':')?;
+                match &self.options.replace {
+                    Some(template) => {
+                        let mut replaced = Vec::new();
+                        captures.expand(template, line, &mut replaced);
+                        output.write_all(&replaced)?;
+                    }
+                    None => output.write_all(&line[found.range()])?,
+                }
+                output.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_context_enabled(&self) -> bool {
+        // Only matched parts are printed with -o, so there is nothing to surround
+        !self.options.only_matching
+            && (self.options.after_context > 0 || self.options.before_context > 0)
+    }
+
+    fn start_input(&mut self) {
+        let context = &mut self.context;
+        context.printed_before |= context.last_printed.is_some();
+        context.last_printed = None;
+        context.after_left = 0;
+        context.before.clear();
+    }
+
+    /// Prints a not selected line as an after context, or keeps it as a possible before context
+    fn handle_context_line(
+        &mut self,
+        line_number: usize,
+        raw_line: &[u8],
+        name: &str,
+        output: &mut impl Write,
+    ) -> io::Result<()> {
+        if !self.is_context_enabled() {
+            return Ok(());
+        }
+        if self.context.after_left > 0 {
+            self.context.after_left -= 1;
+            self.write_separator(line_number, output)?;
+            write_prefix(output, self.prefix(name), '-')?;
+            return write_raw_line(output, raw_line);
+        }
+
+        let before = &mut self.context.before;
+        if self.options.before_context > 0 {
+            if before.len() == self.options.before_context {
+                before.pop_front();
+            }
+            before.push_back((line_number, raw_line.to_vec()));
+        }
+        Ok(())
+    }
+
+    fn print_before_context(&mut self, name: &str, output: &mut impl Write) -> io::Result<()> {
+        while let Some((line_number, raw_line)) = self.context.before.pop_front() {
+            self.write_separator(line_number, output)?;
+            write_prefix(output, self.prefix(name), '-')?;
+            write_raw_line(output, &raw_line)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `--` between the groups of lines that are not adjacent and marks the line printed
+    fn write_separator(&mut self, line_number: usize, output: &mut impl Write) -> io::Result<()> {
+        if !self.is_context_enabled() {
+            return Ok(());
+        }
+        let context = &mut self.context;
+        let is_new_group = match context.last_printed {
+            Some(last_printed) => line_number > last_printed + 1,
+            None => context.printed_before,
+        };
+        context.last_printed = Some(line_number);
+        if is_new_group {
+            output.write_all(b"--\n")?;
+        }
+        Ok(())
+    }
+
+    fn prefix<'n>(&self, name: &'n str) -> Option<&'n str> {
+        self.options.with_filename.then_some(name)
+    }
+}
+
+/// Writes the input name followed by `:` for selected lines or `-` for context lines
+fn write_prefix(output: &mut impl Write, prefix: Option<&str>, separator: char) -> io::Result<()> {
+    match prefix {
+        Some(prefix) => write!(output, "{prefix}{separator}"),
+        None => Ok(()),
+    }
+}
+
+fn write_raw_line(output: &mut impl Write, raw_line: &[u8]) -> io::Result<()> {
+    output.write_all(raw_line)?;
+    if !raw_line.ends_with(b"\n") {
+        output.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Detects binary input the way grep does: by a NUL byte in the first buffered chunk
+pub(crate) fn is_binary(input: &mut impl BufRead) -> io::Result<bool> {
+    Ok(input.fill_buf()?.contains(&0))
+}
+
+/// Strips the line terminator (`\n` or `\r\n`) from a raw input line
+fn trim_line_end(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
@@ -0,0 +1,12 @@
+/// Flavour of the pattern language
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Syntax {
+    /// Extended regular expressions, `*` repeats the previous token zero or more times
+    #[default]
+    Extended,
+    /// Extended regular expressions with a glob-like `*`, which matches one or more of
+    /// any characters, so `*.rs` matches file names
+    Glob,
+    /// Extended regular expressions with Perl extensions: lookahead and lookbehind
+    Perl,
+}
@@ -1,28 +1,60 @@
-use std::str::Bytes;
+use std::slice;
 
-use crate::{pattern::Pattern, pattern_list::PatternList};
+use crate::pattern_list::PatternList;
 
 #[derive(Clone, Debug)]
 pub enum TextToken {
+    /// Matches any of the variants, preferring the earlier ones
     Alteration(Vec<PatternList>),
+    /// Subpattern whose match is recorded as the capture group with the given index,
+    /// reachable by the name too if the group has one
+    Group {
+        index: usize,
+        name: Option<String>,
+        pattern: PatternList,
+    },
+    /// Subpattern matched in the first way it can, the rest of the pattern failing does
+    /// not backtrack into it
+    Atomic(PatternList),
+    /// Text previously matched by the capture group with the given index
+    Backreference {
+        group: usize,
+        case_insensitive: bool,
+    },
+    /// Zero-width check that the subpattern matches right after the position, or right
+    /// before it for a lookbehind. Negated lookarounds check that it does not match.
+    Lookaround {
+        behind: bool,
+        negated: bool,
+        pattern: PatternList,
+    },
 }
 
 impl TextToken {
-    pub fn match_input(&self, input: &Bytes) -> Option<usize> {
+    /// Patterns nested in the token
+    pub fn subpatterns(&self) -> &[PatternList] {
         match self {
-            TextToken::Alteration(variants) => {
-                let input_length = input.len();
-                for variant in variants.clone() {
-                    let mut input_clone = input.clone();
-                    let mut pattern = Pattern::new(variant);
+            TextToken::Alteration(variants) => variants,
+            TextToken::Group { pattern, .. } | TextToken::Atomic(pattern) => {
+                slice::from_ref(pattern)
+            }
+            TextToken::Backreference { .. } => &[],
+            TextToken::Lookaround { pattern, .. } => slice::from_ref(pattern),
+        }
+    }
 
-                    if pattern.match_here(&mut input_clone) {
-                        let new_input_length = input_clone.len();
-                        return Some(input_length - new_input_length);
-                    }
-                }
-                None
+    pub(crate) fn fold_case(&mut self, unicode: bool) {
+        match self {
+            TextToken::Alteration(variants) => variants
+                .iter_mut()
+                .for_each(|variant| variant.fold_case(unicode)),
+            TextToken::Group { pattern, .. } | TextToken::Atomic(pattern) => {
+                pattern.fold_case(unicode)
             }
+            TextToken::Backreference {
+                case_insensitive, ..
+            } => *case_insensitive = true,
+            TextToken::Lookaround { pattern, .. } => pattern.fold_case(unicode),
         }
     }
 }
@@ -1,6 +1,6 @@
+use crate::char_class::CharClass;
 use crate::text_token::TextToken;
 use std::cmp::PartialEq;
-use std::str::Bytes;
 
 #[derive(Clone, Debug)]
 pub(crate) enum Token {
@@ -9,36 +9,123 @@ pub(crate) enum Token {
 }
 
 impl Token {
-    pub(crate) fn match_input(&self, input: &mut Bytes) -> Option<usize> {
+    pub(crate) fn fold_case(&mut self, unicode: bool) {
         match self {
-            Token::Char(token) => token.match_char(&input.next()?).then_some(1),
-            Token::Text(token) => token.match_input(input),
+            Token::Char(token) => *token = token.clone().fold_case(unicode),
+            Token::Text(token) => token.fold_case(unicode),
         }
     }
 }
 
 #[derive(PartialEq, Clone, Debug)]
 pub enum CharToken {
-    Exact(u8),
+    Exact(char),
     Wildcard,
-    Group(Vec<u8>),
-    NegativeGroup(Vec<u8>),
+    Group(CharClass),
+    NegativeGroup(CharClass),
     CharType(CharType),
+    /// `^` in the multi-line mode, start of the input or of a line
     StartLine,
+    /// `$` in the multi-line mode, end of the input or of a line
     EndLine,
+    /// `^` or `\A`, start of the whole input
+    StartText,
+    /// `$` or `\z`, end of the whole input
+    EndText,
+    /// `\b`, between a word character and a non-word character or the input edge.
+    /// `unicode` tells whether words are made of Unicode alphanumerics, like `\w`
+    /// in the Unicode mode
+    WordBoundary {
+        unicode: bool,
+    },
+    /// `\B`, anywhere `\b` does not match
+    NotWordBoundary {
+        unicode: bool,
+    },
+    /// `\<`, before the first character of a word
+    WordStart {
+        unicode: bool,
+    },
+    /// `\>`, after the last character of a word
+    WordEnd {
+        unicode: bool,
+    },
 }
 
 impl CharToken {
-    pub fn match_char(&self, input_ch: &u8) -> bool {
+    /// Makes the token match letters regardless of their case, only the ASCII letters
+    /// unless `unicode` is set
+    pub fn fold_case(self, unicode: bool) -> Self {
         match self {
-            CharToken::Exact(ch) => input_ch == ch,
+            CharToken::Exact(ch) if ch.is_ascii_alphabetic() || unicode => {
+                let mut class = CharClass::new([(ch, ch)]);
+                class.fold_case(unicode);
+                if class.ranges() == [(ch, ch)] {
+                    CharToken::Exact(ch)
+                } else {
+                    CharToken::Group(class)
+                }
+            }
+            CharToken::Group(mut class) => {
+                class.fold_case(unicode);
+                CharToken::Group(class)
+            }
+            CharToken::NegativeGroup(mut class) => {
+                class.fold_case(unicode);
+                CharToken::NegativeGroup(class)
+            }
+            token => token,
+        }
+    }
+
+    /// Whether the token checks a position instead of consuming a character
+    pub fn is_assertion(&self) -> bool {
+        matches!(
+            self,
+            CharToken::StartLine
+                | CharToken::EndLine
+                | CharToken::StartText
+                | CharToken::EndText
+                | CharToken::WordBoundary { .. }
+                | CharToken::NotWordBoundary { .. }
+                | CharToken::WordStart { .. }
+                | CharToken::WordEnd { .. }
+        )
+    }
+
+    pub fn match_char(&self, input_ch: char) -> bool {
+        match self {
+            CharToken::Exact(ch) => input_ch == *ch,
             CharToken::CharType(char_type) => char_type.match_char(input_ch),
-            CharToken::Group(group) => group.contains(input_ch),
-            CharToken::NegativeGroup(group) => !group.contains(input_ch),
+            CharToken::Group(class) => class.contains(input_ch),
+            CharToken::NegativeGroup(class) => !class.contains(input_ch),
             CharToken::Wildcard => true,
             _ => false,
         }
     }
+
+    /// Class of the characters the token matches, empty for assertions
+    pub fn class(&self) -> CharClass {
+        match self {
+            CharToken::Exact(ch) => CharClass::new([(*ch, *ch)]),
+            CharToken::CharType(char_type) => char_type.class(),
+            CharToken::Group(class) => class.clone(),
+            CharToken::NegativeGroup(class) => class.negate(),
+            CharToken::Wildcard => CharClass::default().negate(),
+            _ => CharClass::default(),
+        }
+    }
+}
+
+/// Whether the character is part of a word for the word boundary assertions: `_` and
+/// the characters `\w` matches, ASCII letters and digits or every alphanumeric character
+/// in the Unicode mode, like in GNU grep
+pub(crate) fn is_word_char(ch: char, unicode: bool) -> bool {
+    if unicode {
+        ch == '_' || ch.is_alphanumeric()
+    } else {
+        ch == '_' || CharType::Alphanumeric.match_char(ch)
+    }
 }
 
 #[derive(PartialEq, Clone, Debug)]
@@ -49,10 +136,51 @@ pub enum CharType {
     NonDigit,
     NonAlphanumeric,
     NonWhitespace,
+    /// POSIX `[:alpha:]`
+    Alpha,
+    /// POSIX `[:lower:]`
+    Lower,
+    /// POSIX `[:upper:]`
+    Upper,
+    /// POSIX `[:punct:]`
+    Punct,
+    /// POSIX `[:xdigit:]`
+    HexDigit,
+    /// POSIX `[:blank:]`, space and tab
+    Blank,
+    /// POSIX `[:cntrl:]`
+    Control,
+    /// POSIX `[:graph:]`, visible characters
+    Graph,
+    /// POSIX `[:print:]`, visible characters and space
+    Print,
+    /// POSIX `[:space:]`, whitespace including vertical tab
+    Space,
 }
 
 impl CharType {
-    pub fn match_char(&self, input_ch: &u8) -> bool {
+    /// Type of a POSIX bracket expression class name, like `alpha` of `[[:alpha:]]`
+    pub fn from_posix_name(name: &str) -> Option<Self> {
+        let char_type = match name {
+            "alnum" => CharType::Alphanumeric,
+            "alpha" => CharType::Alpha,
+            "blank" => CharType::Blank,
+            "cntrl" => CharType::Control,
+            "digit" => CharType::Digit,
+            "graph" => CharType::Graph,
+            "lower" => CharType::Lower,
+            "print" => CharType::Print,
+            "punct" => CharType::Punct,
+            "space" => CharType::Space,
+            "upper" => CharType::Upper,
+            "xdigit" => CharType::HexDigit,
+            _ => return None,
+        };
+        Some(char_type)
+    }
+
+    /// Matches ASCII characters only, so the negated types match every other character
+    pub fn match_char(&self, input_ch: char) -> bool {
         match self {
             CharType::Digit => input_ch.is_ascii_digit(),
             CharType::Alphanumeric => input_ch.is_ascii_alphanumeric(),
@@ -60,6 +188,25 @@ impl CharType {
             CharType::NonDigit => !input_ch.is_ascii_digit(),
             CharType::NonAlphanumeric => !input_ch.is_ascii_alphanumeric(),
             CharType::NonWhitespace => !input_ch.is_ascii_whitespace(),
+            CharType::Alpha => input_ch.is_ascii_alphabetic(),
+            CharType::Lower => input_ch.is_ascii_lowercase(),
+            CharType::Upper => input_ch.is_ascii_uppercase(),
+            CharType::Punct => input_ch.is_ascii_punctuation(),
+            CharType::HexDigit => input_ch.is_ascii_hexdigit(),
+            CharType::Blank => matches!(input_ch, ' ' | '\t'),
+            CharType::Control => input_ch.is_ascii_control(),
+            CharType::Graph => input_ch.is_ascii_graphic(),
+            CharType::Print => input_ch.is_ascii_graphic() || input_ch == ' ',
+            CharType::Space => input_ch.is_ascii_whitespace() || input_ch == '\x0B',
+        }
+    }
+
+    /// Class of the same characters the type matches, to be combined in bracket expressions
+    pub fn class(&self) -> CharClass {
+        let mut class = CharClass::from_predicate('\0'..='\x7F', |ch| self.match_char(ch));
+        if self.match_char('\u{80}') {
+            class.push('\u{80}', char::MAX);
         }
+        class
     }
 }
@@ -1,7 +1,20 @@
 pub enum TokenModifier {
     Optional,
+    ZeroOrMore,
     OneOrMore,
     Exact(usize),
     AtLeast(usize),
     Between(usize, usize),
 }
+
+/// How a repetition chooses the number of times it matches
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Greediness {
+    /// As many times as possible, giving back when the rest of the pattern does not match
+    #[default]
+    Greedy,
+    /// As few times as possible, `x*?`
+    Lazy,
+    /// As many times as possible without ever giving back, `x*+`
+    Possessive,
+}
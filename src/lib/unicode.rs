@@ -0,0 +1,154 @@
+use std::ops::RangeInclusive;
+use std::sync::OnceLock;
+
+use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_script::{Script, UnicodeScript};
+
+use crate::char_class::CharClass;
+use crate::token::CharType;
+
+/// Abbreviations of all general categories
+const CATEGORIES: [&str; 30] = [
+    "Lu", "Ll", "Lt", "Lm", "Lo", "Mn", "Mc", "Me", "Nd", "Nl", "No", "Pc", "Pd", "Ps", "Pe", "Pi",
+    "Pf", "Po", "Sm", "Sc", "Sk", "So", "Zs", "Zl", "Zp", "Cc", "Cf", "Cs", "Co", "Cn",
+];
+
+/// Long names of the general category groups, the first letter is the group abbreviation
+const CATEGORY_GROUPS: [&str; 7] = [
+    "Letter",
+    "Mark",
+    "Number",
+    "Punctuation",
+    "Symbol",
+    "Separator",
+    "Other",
+];
+
+/// Class of the `\p{..}` property: a general category like `Lu`, a category group
+/// like `L` or `Letter`, or a script like `Greek` or `Grek`
+pub(crate) fn property_class(name: &str) -> Option<CharClass> {
+    if let Some(class) = category_class(name) {
+        return Some(class);
+    }
+    let script = Script::from_full_name(name).or_else(|| Script::from_short_name(name))?;
+    Some(runs_class(script_runs(), |run_script| run_script == script))
+}
+
+/// Unicode class of `\d`, `\w` and `\s` and their negations, `None` for other types
+pub(crate) fn char_type_class(char_type: &CharType) -> Option<CharClass> {
+    let class = match char_type {
+        CharType::Digit | CharType::NonDigit => category_class("Nd")?,
+        CharType::Alphanumeric | CharType::NonAlphanumeric => assigned_class(char::is_alphanumeric),
+        CharType::Whitespace | CharType::NonWhitespace => assigned_class(char::is_whitespace),
+        _ => return None,
+    };
+    let is_negated = matches!(
+        char_type,
+        CharType::NonDigit | CharType::NonAlphanumeric | CharType::NonWhitespace
+    );
+    Some(if is_negated { class.negate() } else { class })
+}
+
+fn category_class(name: &str) -> Option<CharClass> {
+    let name = match CATEGORY_GROUPS.iter().find(|group| **group == name) {
+        Some(group) => &group[..1],
+        None => name,
+    };
+    let is_group = name.len() == 1 && CATEGORIES.iter().any(|c| c.starts_with(name));
+    if !is_group && !CATEGORIES.contains(&name) {
+        return None;
+    }
+    Some(runs_class(category_runs(), |category| {
+        category.abbreviation().starts_with(name)
+    }))
+}
+
+/// Class of the characters the predicate holds for, which never holds for unassigned,
+/// private use or surrogate characters. Only the characters of other categories are
+/// checked with the predicate.
+fn assigned_class(predicate: impl Fn(char) -> bool) -> CharClass {
+    let ranges = assigned_runs().flat_map(|chars| {
+        CharClass::from_predicate(chars, &predicate)
+            .ranges()
+            .to_vec()
+    });
+    CharClass::new(ranges)
+}
+
+/// Runs of the characters which are not unassigned, private use or surrogate
+fn assigned_runs() -> impl Iterator<Item = RangeInclusive<char>> {
+    category_runs()
+        .iter()
+        .filter(|(_, _, category)| {
+            !matches!(
+                category,
+                GeneralCategory::Unassigned
+                    | GeneralCategory::PrivateUse
+                    | GeneralCategory::Surrogate
+            )
+        })
+        .map(|&(start, end, _)| start..=end)
+}
+
+/// Class of the runs whose value the predicate holds for
+fn runs_class<T: Copy>(runs: &[(char, char, T)], predicate: impl Fn(T) -> bool) -> CharClass {
+    CharClass::new(
+        runs.iter()
+            .filter(|&&(_, _, value)| predicate(value))
+            .map(|&(start, end, _)| (start, end)),
+    )
+}
+
+/// Every character with a simple case mapping paired with the character it maps to, and
+/// the other way round, found on first use
+pub(crate) fn case_pairs() -> &'static [(char, char)] {
+    static PAIRS: OnceLock<Vec<(char, char)>> = OnceLock::new();
+    PAIRS.get_or_init(|| {
+        let mut pairs = Vec::new();
+        for ch in assigned_runs().flatten() {
+            let mappings = [
+                simple_mapping(ch.to_lowercase()),
+                simple_mapping(ch.to_uppercase()),
+            ];
+            for other in mappings.into_iter().flatten().filter(|&other| other != ch) {
+                pairs.extend([(ch, other), (other, ch)]);
+            }
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    })
+}
+
+/// Only character of a case mapping, `None` for the mappings to several characters like
+/// `ß` to `SS`, which are not simple
+fn simple_mapping(mut mapped: impl Iterator<Item = char>) -> Option<char> {
+    let ch = mapped.next()?;
+    mapped.next().is_none().then_some(ch)
+}
+
+/// All characters split into runs of the same general category, found on first use
+fn category_runs() -> &'static [(char, char, GeneralCategory)] {
+    static RUNS: OnceLock<Vec<(char, char, GeneralCategory)>> = OnceLock::new();
+    RUNS.get_or_init(|| find_runs(get_general_category))
+}
+
+/// All characters split into runs of the same script, found on first use
+fn script_runs() -> &'static [(char, char, Script)] {
+    static RUNS: OnceLock<Vec<(char, char, Script)>> = OnceLock::new();
+    RUNS.get_or_init(|| find_runs(|ch| ch.script()))
+}
+
+/// Splits all characters into runs of consecutive characters with the same property,
+/// which takes a single lookup of every character
+fn find_runs<T: PartialEq>(property: impl Fn(char) -> T) -> Vec<(char, char, T)> {
+    let mut runs: Vec<(char, char, T)> = Vec::new();
+    for ch in '\0'..=char::MAX {
+        let value = property(ch);
+        match runs.last_mut() {
+            Some((_, end, last)) if *last == value => *end = ch,
+            _ => runs.push((ch, ch, value)),
+        }
+    }
+    runs
+}
@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::glob::glob_match;
+
+/// Rules used to pick the files searched recursively
+#[derive(Debug, Default)]
+pub(crate) struct WalkOptions {
+    /// Follow symbolic links found inside directories, not only on the command line
+    pub follow_links: bool,
+    /// Globs a file name has to match one of, any name matches when empty
+    pub include: Vec<String>,
+    /// Globs a file name must not match
+    pub exclude: Vec<String>,
+}
+
+impl WalkOptions {
+    pub fn is_file_selected(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name() else {
+            return true;
+        };
+        let name = name.to_string_lossy();
+        let included = self.include.is_empty() || self.include.iter().any(|g| glob_match(g, &name));
+        included && !self.exclude.iter().any(|g| glob_match(g, &name))
+    }
+
+    /// Iterates depth-first, in file name order, over the selected files inside `root`
+    pub fn walk(&self, root: &Path) -> Walk<'_> {
+        Walk {
+            options: self,
+            stack: vec![root.to_path_buf()],
+            at_root: true,
+            visited: HashSet::new(),
+        }
+    }
+}
+
+pub(crate) struct Walk<'a> {
+    options: &'a WalkOptions,
+    stack: Vec<PathBuf>,
+    at_root: bool,
+    /// Canonical paths of entered directories, guards against symbolic link loops
+    visited: HashSet<PathBuf>,
+}
+
+impl Walk<'_> {
+    fn enter_dir(&mut self, dir: &Path) -> io::Result<()> {
+        if self.options.follow_links && !self.visited.insert(fs::canonicalize(dir)?) {
+            return Ok(());
+        }
+
+        let mut entries = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+        self.stack.extend(entries.into_iter().rev());
+        Ok(())
+    }
+}
+
+impl Iterator for Walk<'_> {
+    type Item = Result<PathBuf, (PathBuf, io::Error)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(path) = self.stack.pop() {
+            let is_root = std::mem::replace(&mut self.at_root, false);
+            let metadata = match fs::symlink_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(e) => return Some(Err((path, e))),
+            };
+            // Symbolic links given on the command line are always followed
+            let metadata = if metadata.file_type().is_symlink() {
+                if !is_root && !self.options.follow_links {
+                    continue;
+                }
+                match fs::metadata(&path) {
+                    Ok(metadata) => metadata,
+                    Err(e) => return Some(Err((path, e))),
+                }
+            } else {
+                metadata
+            };
+
+            if metadata.is_dir() {
+                if let Err(e) = self.enter_dir(&path) {
+                    return Some(Err((path, e)));
+                }
+            } else if metadata.is_file() && self.options.is_file_selected(&path) {
+                return Some(Ok(path));
+            }
+        }
+        None
+    }
+}
@@ -9,7 +9,7 @@ fn main() {
         Ok(code) => process::exit(code),
         Err(e) => {
             eprintln!("{e}");
-            process::exit(1)
+            process::exit(2)
         }
     }
 }
@@ -0,0 +1,321 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Creates a fresh directory with the given files for a single test
+fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    for (path, content) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+fn run(args: &[&str], stdin: &str) -> (i32, String) {
+    run_in(&std::env::current_dir().unwrap(), args, stdin)
+}
+
+fn run_in(dir: &Path, args: &[&str], stdin: &str) -> (i32, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_grepr"))
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The command may exit before reading its input, like on an invalid pattern
+    let written = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    if let Err(err) = written {
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    (output.status.code().unwrap(), stdout)
+}
+
+#[test]
+fn stdin_multiple_lines() {
+    let (code, out) = run(&[r"\d"], "foo\nbar 12\nbaz\nqux 3");
+    assert_eq!(code, 0);
+    assert_eq!(out, "bar 12\nqux 3\n");
+}
+
+#[test]
+fn stdin_no_match() {
+    let (code, out) = run(&["dog"], "cat\nbird\n");
+    assert_eq!(code, 1);
+    assert_eq!(out, "");
+}
+
+#[test]
+fn single_file_without_prefix() {
+    let dir = fixture("single_file", &[("a.log", "one\ntwo\nthree\n")]);
+    let (code, out) = run_in(&dir, &["t"], "");
+    assert_eq!((code, out.as_str()), (1, ""));
+    let (code, out) = run_in(&dir, &["t", "a.log"], "");
+    assert_eq!(code, 0);
+    assert_eq!(out, "two\nthree\n");
+}
+
+#[test]
+fn multiple_files_with_prefix() {
+    let dir = fixture(
+        "multiple_files",
+        &[("a.log", "cat\ndog\n"), ("b.log", "dogs\nbird\n")],
+    );
+    let (code, out) = run_in(&dir, &["dog", "a.log", "b.log"], "");
+    assert_eq!(code, 0);
+    assert_eq!(out, "a.log:dog\nb.log:dogs\n");
+}
+
+#[test]
+fn missing_file_is_reported() {
+    let dir = fixture("missing_file", &[("a.log", "dog\n")]);
+    let (code, out) = run_in(&dir, &["dog", "missing.log", "a.log"], "");
+    assert_eq!(code, 2);
+    assert_eq!(out, "a.log:dog\n");
+}
+
+#[test]
+fn recursive_directory() {
+    let dir = fixture(
+        "recursive",
+        &[
+            ("src/main.rs", "fn main() {}\n"),
+            ("src/lib/mod.rs", "mod main;\n"),
+            ("README.md", "main docs\n"),
+            ("data.bin", "main\0binary\n"),
+        ],
+    );
+    let (code, out) = run_in(&dir, &["-r", "main"], "");
+    assert_eq!(code, 0);
+    assert_eq!(
+        out,
+        "README.md:main docs\nsrc/lib/mod.rs:mod main;\nsrc/main.rs:fn main() {}\n"
+    );
+
+    let (_, out) = run_in(&dir, &["-r", "main", "src"], "");
+    assert_eq!(out, "src/lib/mod.rs:mod main;\nsrc/main.rs:fn main() {}\n");
+
+    let (_, out) = run_in(&dir, &["main", "src"], "");
+    assert_eq!(out, "");
+}
+
+#[test]
+fn recursive_include_exclude() {
+    let dir = fixture(
+        "recursive_globs",
+        &[
+            ("a.rs", "todo\n"),
+            ("b.txt", "todo\n"),
+            ("c_test.rs", "todo\n"),
+        ],
+    );
+    let (_, out) = run_in(&dir, &["-r", "--include", "*.rs", "todo"], "");
+    assert_eq!(out, "a.rs:todo\nc_test.rs:todo\n");
+    let (_, out) = run_in(
+        &dir,
+        &["-r", "--include", "*.rs", "--exclude", "*_test.rs", "todo"],
+        "",
+    );
+    assert_eq!(out, "a.rs:todo\n");
+}
+
+#[test]
+fn recursive_include_many_stars() {
+    let long_name = format!("{}.txt", "a".repeat(55));
+    let dir = fixture(
+        "recursive_many_stars",
+        &[(long_name.as_str(), "todo\n"), ("aaaaaaaab.txt", "todo\n")],
+    );
+    // Exponential in the number of stars for a matcher retrying every one of them
+    let (_, out) = run_in(
+        &dir,
+        &["-r", "--include", "*a*a*a*a*a*a*a*a*b*", "todo"],
+        "",
+    );
+    assert_eq!(out, "aaaaaaaab.txt:todo\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn recursive_symlinks() {
+    let dir = fixture(
+        "recursive_symlinks",
+        &[("target/a.txt", "hit\n"), ("root/b.txt", "hit\n")],
+    );
+    std::os::unix::fs::symlink(dir.join("target"), dir.join("root/link")).unwrap();
+
+    let (_, out) = run_in(&dir.join("root"), &["-r", "hit"], "");
+    assert_eq!(out, "b.txt:hit\n");
+    let (_, out) = run_in(&dir.join("root"), &["-R", "hit"], "");
+    assert_eq!(out, "b.txt:hit\nlink/a.txt:hit\n");
+}
+
+#[test]
+fn only_matching() {
+    let (code, out) = run(&["-o", r"\d+"], "a1b22\nnone\n333\n");
+    assert_eq!(code, 0);
+    assert_eq!(out, "1\n22\n333\n");
+
+    let dir = fixture(
+        "only_matching",
+        &[("a.log", "cat dog\n"), ("b.log", "dog\n")],
+    );
+    let (_, out) = run_in(&dir, &["-o", "(cat|dog)", "a.log", "b.log"], "");
+    assert_eq!(out, "a.log:cat\na.log:dog\nb.log:dog\n");
+}
+
+#[test]
+fn only_matching_lazy() {
+    let (code, out) = run(&["-o", "<.+?>"], "<a> and <b>\n");
+    assert_eq!((code, out.as_str()), (0, "<a>\n<b>\n"));
+}
+
+#[test]
+fn utf8_and_binary_lines() {
+    let (code, out) = run(&["-o", "caf."], "café\n");
+    assert_eq!((code, out.as_str()), (0, "café\n"));
+
+    // Not valid UTF-8, so every byte is a character
+    let dir = fixture("latin1", &[]);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("latin1.txt"), b"caf\xe9\nthe\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_grepr"))
+        .args(["^caf.$", "latin1.txt"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"caf\xe9\n");
+}
+
+#[test]
+fn unicode_char_types() {
+    let input = "user=иван\nuser=日本\nuser=-\n";
+    let (code, out) = run(&[r"user=\w+"], input);
+    assert_eq!((code, out.as_str()), (1, ""));
+
+    let (code, out) = run(&["--unicode", r"user=\w+"], input);
+    assert_eq!((code, out.as_str()), (0, "user=иван\nuser=日本\n"));
+}
+
+#[test]
+fn replace_matches() {
+    let input = "user=ann id=1\nnothing\nuser=bob\r\n";
+    let (code, out) = run(&["--replace", "<$name>", r"user=(?<name>\w+)"], input);
+    assert_eq!((code, out.as_str()), (0, "<ann> id=1\n<bob>\r\n"));
+
+    let (code, out) = run(&["-o", "--replace", "${1}!", r"(\w+)="], input);
+    assert_eq!((code, out.as_str()), (0, "user!\nid!\nuser!\n"));
+}
+
+#[test]
+fn invert_match() {
+    let (code, out) = run(&["-v", "DEBUG"], "DEBUG a\nERROR b\nDEBUG c\nINFO d\n");
+    assert_eq!(code, 0);
+    assert_eq!(out, "ERROR b\nINFO d\n");
+
+    let (code, out) = run(&["-v", "a"], "a\nab\n");
+    assert_eq!((code, out.as_str()), (1, ""));
+}
+
+#[test]
+fn ignore_case() {
+    let (code, out) = run(&["-i", "error"], "ERROR a\ninfo\nError b\n");
+    assert_eq!(code, 0);
+    assert_eq!(out, "ERROR a\nError b\n");
+}
+
+#[test]
+fn inline_flags() {
+    let (code, out) = run(&["(?i)error"], "ERROR a\ninfo\nError b\n");
+    assert_eq!((code, out.as_str()), (0, "ERROR a\nError b\n"));
+
+    let (code, out) = run(&["-i", "(?-i:E)rror"], "ERROR a\nerror b\n");
+    assert_eq!((code, out.as_str()), (0, "ERROR a\n"));
+}
+
+#[test]
+fn pattern_syntax() {
+    let (code, out) = run(&["-E", "ab*c"], "ac\nabbc\nadc\n");
+    assert_eq!((code, out.as_str()), (0, "ac\nabbc\n"));
+
+    let (code, out) = run(&["--glob", "*.rs"], "main.rs\n.rs\nCargo.toml\n");
+    assert_eq!((code, out.as_str()), (0, "main.rs\n"));
+
+    let (code, _) = run(&["*.rs"], "main.rs\n");
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn perl_lookaround() {
+    let input = "ERROR disk\nERROR net RETRY\nINFO up\n";
+    let (code, out) = run(&["-P", "ERROR(?!.*RETRY)"], input);
+    assert_eq!((code, out.as_str()), (0, "ERROR disk\n"));
+
+    let (code, out) = run(&["-P", "-o", r"(?<=ERROR )\w+"], input);
+    assert_eq!((code, out.as_str()), (0, "disk\nnet\n"));
+
+    let (code, _) = run(&["-E", "ERROR(?!.*RETRY)"], input);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn count_lines() {
+    let (code, out) = run(&["-c", "a"], "a\nb\naa\n");
+    assert_eq!((code, out.as_str()), (0, "2\n"));
+    let (code, out) = run(&["-c", "-v", "a"], "a\nb\naa\n");
+    assert_eq!((code, out.as_str()), (0, "1\n"));
+    let (code, out) = run(&["-c", "z"], "a\n");
+    assert_eq!((code, out.as_str()), (1, "0\n"));
+
+    let dir = fixture("count_lines", &[("a.log", "x\nx\n"), ("b.log", "y\n")]);
+    let (_, out) = run_in(&dir, &["-c", "x", "a.log", "b.log"], "");
+    assert_eq!(out, "a.log:2\nb.log:0\n");
+}
+
+#[test]
+fn files_with_and_without_matches() {
+    let dir = fixture(
+        "files_with_matches",
+        &[("a.log", "x\nx\n"), ("b.log", "y\n"), ("c.log", "x\n")],
+    );
+    let (code, out) = run_in(&dir, &["-l", "x", "a.log", "b.log", "c.log"], "");
+    assert_eq!((code, out.as_str()), (0, "a.log\nc.log\n"));
+    let (code, out) = run_in(&dir, &["-L", "x", "a.log", "b.log", "c.log"], "");
+    assert_eq!((code, out.as_str()), (0, "b.log\n"));
+    let (code, out) = run_in(&dir, &["-l", "x", "a.log"], "");
+    assert_eq!((code, out.as_str()), (0, "a.log\n"));
+
+    let (_, out) = run(&["-l", "x"], "x\n");
+    assert_eq!(out, "(standard input)\n");
+}
+
+#[test]
+fn context_lines() {
+    let input = "1\n2\nerr a\n4\n5\n6\n7\nerr b\nerr c\n10\n";
+    let (_, out) = run(&["-A", "1", "err"], input);
+    assert_eq!(out, "err a\n4\n--\nerr b\nerr c\n10\n");
+    let (_, out) = run(&["-B", "1", "err"], input);
+    assert_eq!(out, "2\nerr a\n--\n7\nerr b\nerr c\n");
+    let (_, out) = run(&["-C", "2", "err"], input);
+    assert_eq!(out, "1\n2\nerr a\n4\n5\n6\n7\nerr b\nerr c\n10\n");
+    let (_, out) = run(&["-C", "1", "err"], input);
+    assert_eq!(out, "2\nerr a\n4\n--\n7\nerr b\nerr c\n10\n");
+}
+
+#[test]
+fn context_lines_with_filename() {
+    let dir = fixture(
+        "context_lines",
+        &[("a.log", "x\nhit\ny\n"), ("b.log", "hit\nz\n")],
+    );
+    let (_, out) = run_in(&dir, &["-A", "1", "hit", "a.log", "b.log"], "");
+    assert_eq!(out, "a.log:hit\na.log-y\n--\nb.log:hit\nb.log-z\n");
+}
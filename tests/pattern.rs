@@ -1,9 +1,26 @@
-use grepr::Pattern;
+use grepr::{ParseError, Pattern, PatternBuilder, Syntax};
 use std::str::FromStr;
 
+/// Builds the pattern with the settings which `configure` sets on its builder
+fn build_with(pattern_str: &str, configure: impl FnOnce(&mut PatternBuilder)) -> Pattern {
+    let mut builder = PatternBuilder::new(pattern_str);
+    configure(&mut builder);
+    builder.build().unwrap()
+}
+
 fn test_match(input: &str, pattern_str: &str, expected: bool) {
-    let mut pattern = Pattern::from_str(pattern_str).unwrap();
-    let res = pattern.match_line(input).unwrap();
+    test_match_with(input, pattern_str, expected, |_| {});
+}
+
+fn test_match_with(
+    input: &str,
+    pattern_str: &str,
+    expected: bool,
+    configure: impl FnOnce(&mut PatternBuilder),
+) {
+    let res = build_with(pattern_str, configure)
+        .match_line(input)
+        .unwrap();
     assert_eq!(res, expected, "input: {}, pattern: {}", input, pattern_str);
 }
 
@@ -95,7 +112,6 @@ fn wildcard_pattern() {
     test_match("cats", "do.s", false);
     test_match("sddsddssas", ".+as", true);
     test_match("ddsdsaDdsds", ".+as?", true);
-    test_match("mod.rs", "*.rs", true);
 }
 
 #[test]
@@ -134,3 +150,729 @@ fn whitespace_pattern() {
     test_match("do\tg", r"do\sg", true);
     test_match("do\t      g", r"do\s+g", true);
 }
+
+fn test_find(input: &str, pattern_str: &str, expected: Option<(usize, usize)>) {
+    test_find_with(input, pattern_str, expected, |_| {});
+}
+
+fn test_find_with(
+    input: &str,
+    pattern_str: &str,
+    expected: Option<(usize, usize)>,
+    configure: impl FnOnce(&mut PatternBuilder),
+) {
+    let res = build_with(pattern_str, configure)
+        .find(input)
+        .map(|m| (m.start(), m.end()));
+    assert_eq!(res, expected, "input: {}, pattern: {}", input, pattern_str);
+}
+
+fn test_find_iter(input: &str, pattern_str: &str, expected: &[(usize, usize)]) {
+    let mut pattern = Pattern::from_str(pattern_str).unwrap();
+    let res = pattern
+        .find_iter(input)
+        .map(|m| (m.start(), m.end()))
+        .collect::<Vec<_>>();
+    assert_eq!(res, expected, "input: {}, pattern: {}", input, pattern_str);
+}
+
+#[test]
+fn find_pattern() {
+    test_find("abc", "abc", Some((0, 3)));
+    test_find("xxabcx", "abc", Some((2, 5)));
+    test_find("sally has 124 apples", r"\d+", Some((10, 13)));
+    test_find("aabc", "^abc", None);
+    test_find("aabc", "abc$", Some((1, 4)));
+    test_find("a cat", "(dog|cat)", Some((2, 5)));
+    test_find("dog", "cat", None);
+}
+
+#[test]
+fn find_iter_pattern() {
+    test_find_iter("a1b22c333", r"\d+", &[(1, 2), (3, 5), (6, 9)]);
+    test_find_iter("cat dog cat", "(dog|cat)", &[(0, 3), (4, 7), (8, 11)]);
+    test_find_iter("abab", "^ab", &[(0, 2)]);
+    test_find_iter("abc", "x", &[]);
+    test_find_iter("ab", "x?", &[(0, 0), (1, 1), (2, 2)]);
+}
+
+fn test_captures(input: &str, pattern_str: &str, expected: &[Option<&str>]) {
+    let mut pattern = Pattern::from_str(pattern_str).unwrap();
+    let captures = pattern.captures(input).unwrap();
+    let res = captures
+        .iter()
+        .map(|m| m.map(|m| &input[m.range()]))
+        .collect::<Vec<_>>();
+    assert_eq!(res, expected, "input: {}, pattern: {}", input, pattern_str);
+}
+
+#[test]
+fn capture_groups_pattern() {
+    test_captures("a cat!", "(dog|cat)", &[Some("cat"), Some("cat")]);
+    test_captures(
+        "2 dogs and 3 cats",
+        r"(\d) (dog|cat)s and (\d)",
+        &[Some("2 dogs and 3"), Some("2"), Some("dog"), Some("3")],
+    );
+    test_captures("ac", "a(b)?c", &[Some("ac"), None]);
+    assert_eq!(Pattern::from_str("(a)(b|c)").unwrap().group_count(), 2);
+}
+
+#[test]
+fn backreference_pattern() {
+    test_match("cat and cat", r"(cat) and \1", true);
+    test_match("cat and dog", r"(cat) and \1", false);
+    test_match(
+        "grep 101 is doing grep 101 times",
+        r"(\w\w\w\w) (\d\d\d) is doing \1 \2 times",
+        true,
+    );
+    test_match(
+        "abcd is abcd, not efg",
+        r"([abcd]+) is \1, not [^xyz]+",
+        true,
+    );
+    assert!(Pattern::from_str(r"(a) \2").is_err());
+    test_captures("aba", r"(a|b\1)+", &[Some("aba"), Some("ba")]);
+
+    // Alternatives are kept on the heap, so a long line does not overflow the stack
+    let input = "a".repeat(100_000);
+    test_find(&input, r"(a)\1*", Some((0, 100_000)));
+    test_find(&input, r"(a)(?:\1|b)*$", Some((0, 100_000)));
+}
+
+#[test]
+fn case_insensitive_pattern() {
+    let ignore_case = |builder: &mut PatternBuilder| {
+        builder.case_insensitive(true);
+    };
+    test_match("ERROR: disk", "error", false);
+    test_match_with("ERROR: disk", "error", true, ignore_case);
+    test_match_with("Error: disk", "eRRoR", true, ignore_case);
+    test_match_with("Apple", "[a]pple", true, ignore_case);
+    test_match_with("Apple", "[^a]pple", false, ignore_case);
+    test_match_with("BANANA", "[^anb]", false, ignore_case);
+    test_match_with("a DOG", "(cat|dog)", true, ignore_case);
+    test_match_with("Cat and CAT", r"(cat) and \1", true, ignore_case);
+    test_match_with("Cat and dog", r"(cat) and \1", false, ignore_case);
+    test_match_with("1 APPLE", r"\d \w+", true, ignore_case);
+    test_match_with("!", "[Ā]", false, ignore_case);
+    test_match_with("Ā", "[Ā]", true, ignore_case);
+    test_match_with("q", "[ā-ſ]", false, ignore_case);
+    test_match_with("Y", "[x-ſ]", true, ignore_case);
+    test_match_with("w", "[x-ſ]", false, ignore_case);
+    test_match_with(" ", r"\p{Lu}", false, ignore_case);
+    test_match_with("a", r"\p{Lu}", true, ignore_case);
+
+    // Non-ASCII letters are folded only in the Unicode mode
+    let unicode = |builder: &mut PatternBuilder| {
+        builder.case_insensitive(true).unicode(true);
+    };
+    test_match_with("CAFÉ", "café", false, ignore_case);
+    test_match_with("CAFÉ", "café", true, unicode);
+    test_match_with("Ö", "[à-ö]", true, unicode);
+    test_match_with("×", "[à-ö]", false, unicode);
+    test_match_with("É", "[^é]", false, unicode);
+    test_match_with("ΣΊΣΥΦΟΣ", "σίσυφος", true, unicode);
+    test_match_with("\u{212A}", "k", true, unicode);
+    test_match_with("STRASSE", "straße", false, unicode);
+}
+
+#[test]
+fn pathological_pattern() {
+    // Exponential for a backtracking matcher, linear for the compiled one
+    let input = "a".repeat(64);
+    test_match(&input, "(a|aa)+b", false);
+    test_find(&input, "(a|aa)+b", None);
+    test_match(&input, "(a|a)+(a|a)+c$", false);
+    test_find(&input, "^(a|aa)+$", Some((0, 64)));
+
+    // Long chains of instructions not consuming input are followed without recursion
+    test_find("aab", "(a?){100000}", Some((0, 2)));
+    test_find("aab", "(?:a?){300000}b", Some((0, 3)));
+}
+
+#[test]
+fn nested_groups_pattern() {
+    test_match("abcd", "a(b(c|x))d", true);
+    test_match("abxd", "a(b(c|x))d", true);
+    test_match("abyd", "a(b(c|x))d", false);
+    test_captures("abcd", "a(b(c|x))d", &[Some("abcd"), Some("bc"), Some("c")]);
+    test_match("cat", "cat|dog", true);
+    test_match("hotdog", "cat|dog", true);
+    test_match("bird", "cat|dog", false);
+}
+
+#[test]
+fn escaped_metacharacters_pattern() {
+    test_match("f(x)", r"f\(x\)", true);
+    test_match("a|b", r"(a\|b)", true);
+    test_match("ab", r"(a\|b)", false);
+    test_match("(", r"(\()", true);
+}
+
+#[test]
+fn anchors_inside_pattern() {
+    test_match("ab", "a^b", false);
+    test_match("b", "(^b|c$)", true);
+    test_match("abc", "(^b|c$)", true);
+    test_match("bca", "(^x|c$)", false);
+    test_match("x", "^$|x", true);
+    test_match("", "^$", true);
+}
+
+fn test_parse_error(pattern_str: &str, span: std::ops::Range<usize>) {
+    let err = Pattern::from_str(pattern_str).err().unwrap();
+    let err = err.downcast::<ParseError>().unwrap();
+    assert_eq!(err.span(), span, "pattern: {}, error: {}", pattern_str, err);
+}
+
+#[test]
+fn parse_errors() {
+    test_parse_error("ab(cd", 2..3);
+    test_parse_error("ab)cd", 2..3);
+    test_parse_error("a[bc", 1..2);
+    test_parse_error("+a", 0..1);
+    test_parse_error("(|?)", 2..3);
+    test_parse_error("a{x}", 1..4);
+    test_parse_error("a{3,1}", 1..6);
+    test_parse_error("ab\\", 2..3);
+    test_parse_error("a{4294967296}", 1..13);
+    test_parse_error("((((a{100}){100}){100}){100})", 23..28);
+    test_parse_error("a{2000000}b{2000000}", 0..20);
+    test_parse_error(r"(a) \2", 4..6);
+    test_parse_error(r"(a)\1\3", 5..7);
+
+    let err = Pattern::from_str("ab)cd").err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "incorrect group pattern: ')' without '('\n    ab)cd\n      ^"
+    );
+}
+
+#[test]
+fn escape_sequence_pattern() {
+    test_match("a\tb", r"a\tb", true);
+    test_match("a b", r"a\tb", false);
+    test_match("a\r\n", r"\r\n$", true);
+    test_match("ABC", r"\x41\x42", true);
+    test_match("x\u{1F600}y", r"x\u{1F600}y", true);
+    test_match("café", r"caf\u{e9}", true);
+    test_match("a\nb", r"a\012b", true);
+    test_match("a\0b", r"a\0b", true);
+    test_match("a\u{1B}[0m", r"\e\[0m", true);
+    test_match("a\u{7}b", r"[\x00-\x1F]", true);
+    test_match("ab", r"[\x00-\x1F]", false);
+    test_match("a.b c", r"a\.b\ c", true);
+    test_find("tab\there", r"[\t\n]", Some((3, 4)));
+}
+
+#[test]
+fn escape_sequence_errors() {
+    test_parse_error(r"a\q", 1..3);
+    test_parse_error(r"[a\q]", 2..4);
+    test_parse_error(r"\xZ1", 0..2);
+    test_parse_error(r"\x4", 0..3);
+    test_parse_error(r"\u12", 0..4);
+    test_parse_error(r"\u{12", 0..5);
+    test_parse_error(r"\u{110000}", 0..10);
+    test_parse_error(r"\é", 0..3);
+
+    let err = Pattern::from_str(r"a\u{D800}").err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "incorrect pattern: invalid escape \\u{D800}\n    a\\u{D800}\n     ^^^^^^^^"
+    );
+}
+
+#[test]
+fn repeated_group_pattern() {
+    test_match("catdogcat", "^(cat|dog)+$", true);
+    test_match("catdogbird", "^(cat|dog)+$", false);
+    test_match("abab", "^(ab){2,3}$", true);
+    test_match("ababab", "^(ab){2,3}$", true);
+    test_match("ab", "^(ab){2,3}$", false);
+    test_match("abababab", "^(ab){2,3}$", false);
+    test_match("", "^((ab)+c)*$", true);
+    test_match("c", "^((ab)+c)*$", false);
+    test_match("abcababc", "^((ab)+c)*$", true);
+    test_match("abcac", "^((ab)+c)*$", false);
+    test_match("xx", "^(a|)+xx$", true);
+    test_match("aaaaaa", "^a{2}{3}$", true);
+    test_match("aaaaa", "^a{2}{3}$", false);
+    test_match("abab", "^(ab)?{2}$", true);
+    test_find("dogdogcat!", "(cat|dog)+", Some((0, 9)));
+    test_find_iter("ab ababab a", "(ab){2,}|a", &[(0, 1), (3, 9), (10, 11)]);
+}
+
+#[test]
+fn repeated_group_captures_pattern() {
+    // A repeated group captures its last iteration
+    test_captures("catdog", "(cat|dog)+", &[Some("catdog"), Some("dog")]);
+    test_captures(
+        "ababcabc",
+        "((ab)+c)+",
+        &[Some("ababcabc"), Some("abc"), Some("ab")],
+    );
+    test_captures(
+        "ab",
+        "((a)|(b))+",
+        &[Some("ab"), Some("b"), Some("a"), Some("b")],
+    );
+    test_match("abab", r"(ab){2}\1", false);
+    test_captures("ababab", r"(ab){2}\1", &[Some("ababab"), Some("ab")]);
+    test_captures(
+        "dogdogdog",
+        r"((dog)+)-?\1",
+        &[Some("dogdog"), Some("dog"), Some("dog")],
+    );
+}
+
+#[test]
+fn kleene_star_pattern() {
+    test_match("ac", "^ab*c$", true);
+    test_match("abbbc", "^ab*c$", true);
+    test_match("adc", "^ab*c$", false);
+    test_match("", "^(ab)*$", true);
+    test_match("ababab", "^(ab)*$", true);
+    test_match("mod.rs", r".*\.rs$", true);
+    test_find("xaaay", "a*y", Some((1, 5)));
+    test_find("xy", "a*", Some((0, 0)));
+    test_parse_error("*.rs", 0..1);
+}
+
+#[test]
+fn glob_syntax_pattern() {
+    let glob = |builder: &mut PatternBuilder| {
+        builder.syntax(Syntax::Glob);
+    };
+    test_match_with("mod.rs", "*.rs", true, glob);
+    test_match_with(".rs", "*.rs", false, glob);
+    test_match_with("mod.rs", "^mod*", true, glob);
+    test_match_with("mod", "^mod*", false, glob);
+    test_match_with("abbc", "ab+c", true, glob);
+}
+
+#[test]
+fn lazy_quantifier_pattern() {
+    test_find("<a><b>", "<.+?>", Some((0, 3)));
+    test_find("<a><b>", "<.*?>", Some((0, 3)));
+    test_find("aaa", "a+?", Some((0, 1)));
+    test_find("aaa", "a??", Some((0, 0)));
+    test_find("aaaa", "a{2,3}?", Some((0, 2)));
+    test_find("aaab", "a{2,}?b", Some((0, 4)));
+    test_find_iter("<a><b>", "<.+?>", &[(0, 3), (3, 6)]);
+    test_captures(
+        "catdogcat",
+        "(cat|dog)+?(dog|cat)$",
+        &[Some("catdogcat"), Some("dog"), Some("cat")],
+    );
+    test_captures("abab", "(.*?)b", &[Some("ab"), Some("a")]);
+}
+
+#[test]
+fn possessive_quantifier_pattern() {
+    let perl = |builder: &mut PatternBuilder| {
+        builder.syntax(Syntax::Perl);
+    };
+    test_match("aaa", "a++a", false);
+    test_match("aaa", "a*+a", false);
+    test_match("aaa", "^a{1,2}+a$", true);
+    test_match("aa", "^a{1,2}+a$", false);
+    test_match("ab", "^a?+ab", false);
+    test_match("b", "^a?+b", true);
+    test_match("abc", "(ab|a)++c", true);
+    test_match("abab", "^(ab|a)*+b$", false);
+    test_find("xaaay", "a++y", Some((1, 5)));
+    test_captures("aab", "(a)++(b)", &[Some("aab"), Some("a"), Some("b")]);
+
+    // Giving back nothing cuts off the exponential backtracking
+    let input = "a".repeat(40);
+    test_match(&input, "^(a++)+b", false);
+    test_match(&input, r"^(\w++\s?)*$", true);
+    test_match(&format!("{input}!"), r"^(\w++\s?)*+$", false);
+
+    // Backtracking elsewhere in the pattern tries every position at most once
+    test_match(&input, "(a|aa)*x*+c", false);
+    test_find(&input, "(a|aa)*(?>x?)b", None);
+    test_find_with(&input, "(a|aa)*(?=b)", None, perl);
+    test_find_with(&input, "^(a|aa)*(?<!a)c", None, perl);
+
+    // Possessive loops are run from every position at most once, so a long line takes
+    // no quadratic time
+    let input = "a".repeat(20_000);
+    test_find(&input, "a*+b", None);
+    test_find(&input, "(a|b)*+c", None);
+    test_find(&input, "a{2,}+b", None);
+}
+
+#[test]
+fn class_ranges_pattern() {
+    let ignore_case = |builder: &mut PatternBuilder| {
+        builder.case_insensitive(true);
+    };
+    test_match("m", "^[a-z]$", true);
+    test_match("-", "^[a-z]$", false);
+    test_match("7", "^[a-z0-9]$", true);
+    test_match("M", "^[a-z0-9]$", false);
+    test_match("x-y", "^[a-z-]+$", true);
+    test_match("-", "^[-a]$", true);
+    test_match("q", "^[^a-p]$", true);
+    test_match("b", "^[^a-p]$", false);
+    test_match("]", "^[]a]$", true);
+    test_match("]", "^[^]a]$", false);
+    test_match("b", "^[^]a]$", true);
+    test_match("M", "^[a-z]$", false);
+    test_match_with("M", "^[a-z]$", true, ignore_case);
+    test_match_with("m", "^[^a-z]$", false, ignore_case);
+}
+
+#[test]
+fn class_escapes_pattern() {
+    test_match("_", r"^[\d_]$", true);
+    test_match("4", r"^[\d_]$", true);
+    test_match("\\", r"^[\d_]$", false);
+    test_match("]", r"^[\]]$", true);
+    test_match("\\", r"^[\\]$", true);
+    test_match("^", r"^[\^]$", true);
+    test_match("a b", r"^[\w\s]+$", true);
+    test_match("a", r"^[\D]$", true);
+    test_match("1", r"^[^\D]$", true);
+    test_match("-", r"^[a\-z]$", true);
+    test_match("b", r"^[a\-z]$", false);
+}
+
+#[test]
+fn posix_class_pattern() {
+    let ignore_case = |builder: &mut PatternBuilder| {
+        builder.case_insensitive(true);
+    };
+    test_match("abc", "^[[:alpha:]]+$", true);
+    test_match("ab1", "^[[:alpha:]]+$", false);
+    test_match("ab1", "^[[:alnum:]]+$", true);
+    test_match("42", "^[[:digit:]]+$", true);
+    test_match("\t \x0B", "^[[:space:]]+$", true);
+    test_match("\t ", "^[[:blank:]]+$", true);
+    test_match("\n", "^[[:blank:]]$", false);
+    test_match("!?.,", "^[[:punct:]]+$", true);
+    test_match("_", "^[[:punct:]]$", true);
+    test_match("a", "^[[:punct:]]$", false);
+    test_match("0fA9", "^[[:xdigit:]]+$", true);
+    test_match("g", "^[[:xdigit:]]$", false);
+    test_match("ab", "^[[:lower:]]+$", true);
+    test_match("aB", "^[[:lower:]]+$", false);
+    test_match("AB", "^[[:upper:]]+$", true);
+    test_match("\x07", "^[[:cntrl:]]$", true);
+    test_match(" ", "^[[:graph:]]$", false);
+    test_match(" ", "^[[:print:]]$", true);
+    test_match("a-1_", "^[[:alpha:][:digit:]_-]+$", true);
+    test_match("1", "^[^[:alpha:]]$", true);
+    test_match("1", "^[[:^alpha:]]$", true);
+    test_match("a", "^[[:^alpha:]]$", false);
+    test_match_with("aB", "^[[:lower:]]+$", true, ignore_case);
+    test_match(":", "^[[:]$", true);
+}
+
+#[test]
+fn class_parse_errors() {
+    test_parse_error("x[z-a]", 2..5);
+    test_parse_error(r"[a-\d]", 1..5);
+    test_parse_error("[]", 0..1);
+    test_parse_error("ab[a-", 2..3);
+    test_parse_error("[[:alfa:]]", 1..9);
+    test_parse_error("[a-[:digit:]]", 1..12);
+}
+
+#[test]
+fn utf8_pattern() {
+    test_match("é", "^.$", true);
+    test_find("café!", "caf.", Some((0, 5)));
+    test_find("é", "[éa]", Some((0, 2)));
+    test_match("é", "^[^a]$", true);
+    test_match("é", "^[^é]$", false);
+    test_match("привет", "^[а-я]+$", true);
+    test_match("hello", "^[а-я]+$", false);
+    test_match("日本", "^..$", true);
+    test_match("é", r"^\W$", true);
+    test_find_iter("héllo", ".", &[(0, 1), (1, 3), (3, 4), (4, 5), (5, 6)]);
+    test_find_iter("é", "", &[(0, 0), (2, 2)]);
+    test_captures("über", "(.)(.)", &[Some("üb"), Some("ü"), Some("b")]);
+    test_match("éé", r"^(é)\1$", true);
+    test_match("ab", "^a{0,}é*b$", true);
+}
+
+#[test]
+fn invalid_utf8_pattern() {
+    // Input that is not valid UTF-8 is matched byte by byte
+    let mut pattern = Pattern::from_str("^caf.$").unwrap();
+    assert!(pattern.match_line_bytes(b"caf\xe9").unwrap());
+    assert!(!pattern.match_line_bytes(b"caf\xe9\xff").unwrap());
+
+    let mut pattern = Pattern::from_str("[^a-z]+").unwrap();
+    let found = pattern.find_bytes(b"ab\xff\xfecd").unwrap();
+    assert_eq!(found.range(), 2..4);
+
+    let mut pattern = Pattern::from_str(r"(\w+)\W(\w+)").unwrap();
+    let captures = pattern.captures_bytes(b"ab\x80cd").unwrap();
+    assert_eq!(captures.get(1).unwrap().range(), 0..2);
+    assert_eq!(captures.get(2).unwrap().range(), 3..5);
+
+    let mut pattern = Pattern::from_str(".").unwrap();
+    assert_eq!(pattern.find_iter_bytes(b"\xc3\xa9\xff").count(), 3);
+    assert_eq!(pattern.find_iter_bytes("é".as_bytes()).count(), 1);
+}
+
+#[test]
+fn unicode_property_pattern() {
+    test_match("привет", r"^\p{L}+$", true);
+    test_match("Été", r"^\p{Lu}", true);
+    test_match("été", r"^\p{Lu}", false);
+    test_match("ab1", r"^\pL+$", false);
+    test_match("αβγ", r"^\p{Greek}+$", true);
+    test_match("abc", r"\p{Grek}", false);
+    test_match("x٣", r"\p{Nd}", true);
+    test_match("12", r"\P{N}", false);
+    test_match("1a", r"\P{Number}", true);
+    test_match("日本 1", r"^[\p{Han}\d ]+$", true);
+    test_match("日本", r"^[^\p{Han}]", false);
+    test_match("!", r"^\p{P}$", true);
+    test_parse_error(r"a\p{Klingon}", 1..12);
+    test_parse_error(r"\p{L", 0..4);
+    test_parse_error(r"[\pX]", 1..4);
+}
+
+#[test]
+fn unicode_char_type_pattern() {
+    let unicode = |builder: &mut PatternBuilder| {
+        builder.unicode(true);
+    };
+    test_match("привет", r"^\w+$", false);
+    test_match_with("привет", r"^\w+$", true, unicode);
+    test_match_with("日本語1", r"^\w+$", true, unicode);
+    test_match_with("٣", r"^\d$", true, unicode);
+    test_match_with("a\u{3000}b", r"a\sb", true, unicode);
+    test_match_with("é", r"^\W$", false, unicode);
+    test_match_with("日本 語", r"^[\w\s]+$", true, unicode);
+    test_match_with("-", r"^[^\w]$", true, unicode);
+}
+
+#[test]
+fn word_boundary_pattern() {
+    let unicode = |builder: &mut PatternBuilder| {
+        builder.unicode(true);
+    };
+    test_find("cat concat cats", r"\bcat\b", Some((0, 3)));
+    test_find("concat cat", r"\bcat\b", Some((7, 10)));
+    test_match("concatenate", r"\bcat", false);
+    test_find("concatenate", r"\Bcat\B", Some((3, 6)));
+    test_find_iter("one two_2 три", r"\<.", &[(0, 1), (4, 5)]);
+    test_find_iter("a-b", r"\<.", &[(0, 1), (2, 3)]);
+    test_find_iter("a-b", r".\>", &[(0, 1), (2, 3)]);
+    test_match("a b", r"a\>\<b", false);
+    test_match("", r"\b", false);
+    test_match("", r"\B", true);
+    test_match("a_b", r"a\b_", false);
+    test_find("a_b c", r"\w\b", Some((2, 3)));
+    test_match("иван", r"\w\b", false);
+    test_match("иван", r"\bи", false);
+    test_match("иван", r"\Bи\B", true);
+    test_match_with("a_b", r"a\b_", false, unicode);
+    test_find_with("иван_ x", r"\w\b", Some((10, 11)), unicode);
+    test_match_with("иван", r"\w\b", true, unicode);
+    test_match_with("иван", r"\bи", true, unicode);
+    test_match_with("иван", r"\<иван\>", true, unicode);
+    test_captures(
+        "foo bar",
+        r"(\w+)\b(.*)",
+        &[Some("foo bar"), Some("foo"), Some(" bar")],
+    );
+}
+
+#[test]
+fn text_anchors_pattern() {
+    test_match("abc", r"\Aabc\z", true);
+    test_match("xabc", r"\Aabc", false);
+    test_match("abcx", r"abc\z", false);
+    test_find_iter("aa", r"\Aa", &[(0, 1)]);
+    test_match("ab", r"a\zb", false);
+}
+
+#[test]
+fn anchors_in_alteration_pattern() {
+    test_find("xab", "(^a|b$)", Some((2, 3)));
+    test_find("abx", "(^a|b$)", Some((0, 1)));
+    test_match("xabx", "(^a|b$)", false);
+    test_match("xb", "x(^b|b)", true);
+    test_match("ab", "a(^b|c)", false);
+    test_find_iter("abab", "a$|^a", &[(0, 1)]);
+}
+
+#[test]
+fn lookahead_pattern() {
+    let perl = |builder: &mut PatternBuilder| {
+        builder.syntax(Syntax::Perl);
+    };
+    test_find_with("ERROR x", "ERROR(?!.*RETRY)", Some((0, 5)), perl);
+    test_find_with("ERROR x RETRY", "ERROR(?!.*RETRY)", None, perl);
+    test_find_with("ERROR ERROR RETRY", "ERROR(?= RETRY)", Some((6, 11)), perl);
+    test_find_with("price: 100$", r"\d+(?=\$)", Some((7, 10)), perl);
+    test_find_with("abc", "(?=b)", Some((1, 1)), perl);
+    test_find_with("aaa", "(?!a)", Some((3, 3)), perl);
+    test_find_with("foobar", "^(?=.*bar)foo", Some((0, 3)), perl);
+
+    let captures = build_with("(?=(a+))a", perl).captures("aaa").unwrap();
+    assert_eq!(captures.get(1).unwrap().range(), 0..3);
+    let captures = build_with("(?!(b))(a)", perl).captures("a").unwrap();
+    assert!(captures.get(1).is_none());
+
+    // Lookaheads at later positions reuse what the earlier ones tried, so a long line
+    // takes no quadratic time
+    let input = "a".repeat(20_000);
+    test_find_with(&input, "a(?=a*b)", None, perl);
+    test_find_with(&input, "a(?!a*$)", None, perl);
+}
+
+#[test]
+fn lookbehind_pattern() {
+    let perl = |builder: &mut PatternBuilder| {
+        builder.syntax(Syntax::Perl);
+    };
+    test_find_with("USD100 EUR200", r"(?<=EUR)\d+", Some((10, 13)), perl);
+    test_find_with("x 100 $200", r"(?<!\$)\b\d+", Some((2, 5)), perl);
+    test_find_with("$100", r"(?<!\$)\b\d+", None, perl);
+    test_find_with("ab ab", "(?<=^|b )ab", Some((0, 2)), perl);
+    test_find_with("cab aab", "(?<=(c|aa)a?)b", Some((2, 3)), perl);
+    test_find_with("éa", "(?<=é)a", Some((2, 3)), perl);
+    test_find_with("aXb", "(?<=a.{0,2})b", Some((2, 3)), perl);
+
+    let mut pattern = build_with("(?<=caf.)!", perl);
+    assert_eq!(pattern.find_bytes(b"caf\xe9!").unwrap().range(), 4..5);
+
+    // Lookbehinds reuse what was tried at the positions they look behind
+    let input = "a".repeat(20_000);
+    test_find_with(&input, "(?<=a{1,2}(?=a*b))", None, perl);
+}
+
+#[test]
+fn lookaround_parse_errors() {
+    // Lookaround is available only in the Perl syntax
+    test_parse_error("a(?=b)", 1..4);
+    test_parse_error("(?<!a)b", 0..4);
+
+    let err = PatternBuilder::new("(?<=a+)b")
+        .syntax(Syntax::Perl)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.downcast::<ParseError>().unwrap().span(), 0..7);
+}
+
+#[test]
+fn non_capturing_group_pattern() {
+    test_captures("abab", "(?:ab)+", &[Some("abab")]);
+    test_captures(
+        "cat dog",
+        "(?:cat|dog) (dog)",
+        &[Some("cat dog"), Some("dog")],
+    );
+    test_match("ac", "^a(?:b|)c$", true);
+    test_captures("ab", "(?:(a)|b)(b)", &[Some("ab"), Some("a"), Some("b")]);
+    test_parse_error("(?:ab", 0..1);
+    test_parse_error("a(?z)", 1..4);
+}
+
+#[test]
+fn named_group_pattern() {
+    let mut pattern = Pattern::from_str(r"(?<user>\w+)@(?P<host>\w+)").unwrap();
+    assert_eq!(pattern.group_count(), 2);
+    let captures = pattern.captures("mail: ann@example").unwrap();
+    assert_eq!(captures.name("user").unwrap().range(), 6..9);
+    assert_eq!(captures.name("host").unwrap().range(), 10..17);
+    assert_eq!(captures.get(2), captures.name("host"));
+    assert!(captures.name("port").is_none());
+
+    let mut pattern = Pattern::from_str("(a)(?<b>b)?c").unwrap();
+    let captures = pattern.captures("ac").unwrap();
+    assert!(captures.name("b").is_none());
+
+    test_parse_error("(?<1a>x)", 0..6);
+    test_parse_error("(?<a>x)(?<a>y)", 7..12);
+    test_parse_error("(?P<ab", 0..6);
+}
+
+#[test]
+fn atomic_group_pattern() {
+    test_match("aaab", "^(?>a+)ab", false);
+    test_match("aaab", "^(?>a+)b", true);
+    test_match("abc", "^(?>ab|a)bc", false);
+    test_match("abc", "^(?>a|ab)bc", true);
+    test_captures("xaab", "(?>(a+))b", &[Some("aab"), Some("aa")]);
+    test_match("foobar", r"\b(?>foo|foobar)\b", false);
+
+    // An atomic group entered at a later position ends where its earlier match did
+    let input = "a".repeat(20_000);
+    test_find(&input, "(?>a*)b", None);
+    test_find(&input, "(?>a|ab)*c", None);
+}
+
+#[test]
+fn captures_expand() {
+    let mut pattern = Pattern::from_str(r"(?<key>\w+)=(\w+)").unwrap();
+    let input = "id=42";
+    let captures = pattern.captures(input).unwrap();
+    let expand = |template: &str| {
+        let mut output = Vec::new();
+        captures.expand(template, input.as_bytes(), &mut output);
+        String::from_utf8(output).unwrap()
+    };
+    assert_eq!(expand("$2:$key"), "42:id");
+    assert_eq!(expand("${key}s ${2}0"), "ids 420");
+    assert_eq!(expand("$$1 $ $3 $nope ${2"), "$1 $   ${2");
+
+    let mut pattern = Pattern::from_str(r"(\d)").unwrap();
+    let found = pattern
+        .captures_iter("a1b2")
+        .map(|captures| captures.get(1).unwrap().range())
+        .collect::<Vec<_>>();
+    assert_eq!(found, vec![1..2, 3..4]);
+}
+
+#[test]
+fn inline_flags_pattern() {
+    let ignore_case = |builder: &mut PatternBuilder| {
+        builder.case_insensitive(true);
+    };
+    test_match("ERROR: disk", "(?i)error", true);
+    test_match("ABCdef", "(?i:abc)def", true);
+    test_match("ABCDEF", "(?i:abc)def", false);
+    test_match("C", "a(?i)b|c", true);
+    test_match("aBc", "^(a(?i)b)c$", true);
+    test_match("aBC", "^(a(?i)b)c$", false);
+    test_match("A", "(?i)(?-i)a", false);
+    test_match("Ab", "(?i)[a-c](?-i:b)", true);
+    test_match("Ab", "(?i)(a)\\1", false);
+    test_match("aA", "(?i)(a)\\1", true);
+    test_match_with("aB", "(?-i:a)b", true, ignore_case);
+    test_match_with("AB", "(?-i:a)b", false, ignore_case);
+
+    test_find("a\nb\nc", "(?m)^b$", Some((2, 3)));
+    test_match("a\nb\nc", "^b$", false);
+    test_find_iter("a\nb", "(?m)$", &[(1, 1), (3, 3)]);
+    test_find_iter("a\nb", "(?m)^.", &[(0, 1), (2, 3)]);
+    test_match("a\nb", "(?m)a$\n^b", true);
+
+    test_match("a\nb", "a.b", false);
+    test_match("a\nb", "(?s)a.b", true);
+    test_match("a\nb\n", "(?s:a.)b.", false);
+    test_find("ab\ncd", "(?s).+", Some((0, 5)));
+
+    test_match("abc", "(?x) a b # comment\n c", true);
+    test_match("a b", r"(?x)a\ b", true);
+    test_match(" ", "(?x)[ ]", true);
+    test_find("aaa", "(?x)a +", Some((0, 3)));
+    test_match("a#", "(?x:a)#", true);
+    test_match("ab", "(?ix) A  B", true);
+
+    test_parse_error("(?i-s-m)a", 0..8);
+    test_parse_error("(?i", 0..3);
+}
@@ -0,0 +1,251 @@
+//! Compares the matches of grepr with the `regex` crate, which serves as the reference engine
+
+use grepr::Pattern;
+use regex::Regex;
+use std::str::FromStr;
+
+type Spans = Vec<Option<(usize, usize)>>;
+
+/// Pattern for the reference engine, whose `\d` and `\w` match Unicode digits and letters
+/// and which spells the word start and end assertions differently
+fn reference_pattern(pattern_str: &str) -> String {
+    pattern_str
+        .replace(r"\d", "[0-9]")
+        .replace(r"\w", "[0-9A-Za-z]")
+        .replace(r"\b", r"(?-u:\b)")
+        .replace(r"\B", r"(?-u:\B)")
+        .replace(r"\<", r"(?-u:\b{start})")
+        .replace(r"\>", r"(?-u:\b{end})")
+}
+
+fn compare(input: &str, pattern_str: &str) {
+    let mut pattern = Pattern::from_str(pattern_str).unwrap();
+    let reference = Regex::new(&reference_pattern(pattern_str)).unwrap();
+
+    let res: Option<Spans> = pattern
+        .captures(input)
+        .map(|c| c.iter().map(|m| m.map(|m| (m.start(), m.end()))).collect());
+    let expected: Option<Spans> = reference
+        .captures(input)
+        .map(|c| c.iter().map(|m| m.map(|m| (m.start(), m.end()))).collect());
+    assert_eq!(res, expected, "input: {input:?}, pattern: {pattern_str}");
+
+    let is_match = pattern.match_line(input).unwrap();
+    let expected = reference.is_match(input);
+    assert_eq!(
+        is_match, expected,
+        "input: {input:?}, pattern: {pattern_str}"
+    );
+}
+
+#[test]
+fn quantifier_backtracking() {
+    compare("aaab", "a+ab");
+    compare("123", r"\d+\d\d");
+    compare("12", r"\d+\d\d");
+    compare("aaa", "a{1,3}a{2}");
+    compare("aaaa", "a?a?aa");
+    compare("xaaay", "a+a+y");
+    compare("abab", ".+b");
+    compare("dogs and dog", ".+dog");
+    compare("sally has 3 apples", r"\w+ has \d+ apples?");
+}
+
+#[test]
+fn nested_groups() {
+    compare("abcabd", "(a(b(c|d)))+");
+    compare("xaby", "x((a|ab)(y|by))");
+    compare("dogcat", "(cat|dog)|(dogcat)");
+    compare("abab", "((ab)|(a))+");
+    compare("b", "a|b|c");
+    compare("abc", "^a|c$");
+}
+
+#[test]
+fn repeated_groups() {
+    compare("catdogcat", "(cat|dog)+");
+    compare("abababab", "(ab){2,3}");
+    compare("ababcabcc", "((ab)+c){0,}");
+    compare("aab", "(a|ab){0,}b");
+    compare("abab", "((a)|(b)){3}");
+    compare("aaa", "(a{0,}){0,}");
+    compare("aaa", "(a*)*");
+    compare("abcabd", "(a|b)*c*(ab)*");
+    compare("aaa", "((a?){2}){2,}");
+    compare("aaaaaaa", "a{2}{3}");
+    compare("abababab", "(ab)?{2}");
+    compare("xaaay", "xa+{2}y");
+    compare(" BB", "([^a]*?)*[A-Za-z]");
+    compare(" \tA", "(( |[^a]*?)+)");
+    compare(" BB", "([^a]*?){2,}[A-Za-z]");
+}
+
+#[test]
+fn lazy_quantifiers() {
+    compare("<a><b>", "<.+?>");
+    compare("aaab", "a*?(a+)b");
+    compare("abcabc", "(.*?)(c|bc)");
+    compare("aaa", "(a|aa)+?$");
+    compare("xyz", "(a*?)*x");
+}
+
+#[test]
+fn class_ranges() {
+    compare("x-y", "[a-z-]+");
+    compare("a_1\\", r"[\d_]+");
+    compare("]]a", "[]a]+");
+    compare("Hello World", "[A-Z][^A-Z ]*");
+    compare("a\tb", r"[\s\d]");
+    compare("x = f(a, b);", "[[:alpha:]]+|[[:punct:]]+");
+    compare("0x1F g", "[[:xdigit:][:space:]]+");
+    compare("ab1", "[[:^alpha:]]");
+}
+
+#[test]
+fn utf8_input() {
+    compare("héllo", "h.l");
+    compare("日本語", "(.)(.)$");
+    compare("привет мир", "[а-я]+ [^а-в]+");
+    compare("éa", r"\w+");
+    compare("naïve", "[^a-z]");
+    compare("ab Σβ 12", r"\p{Greek}+");
+    compare("Δx٣y", r"\p{Lu}|\p{Nd}");
+    compare("日本語 text", r"[\p{Han}\s]+\P{L}");
+}
+
+#[test]
+fn assertions() {
+    compare("cat concat cats", r"\bcat\b");
+    compare("concatenate", r"\Bcat\B");
+    compare("foo_bar baz", r"\b\w+\b");
+    compare("иван петров", r"\b.");
+    compare("иван_2 ab_c", r"\w\b");
+    compare("иван_2 ab_c", r"\B.\b");
+    compare("a-b c", r"\<(.)(.*)\>");
+    compare("abc", r"\A(a|c)|(b|c)\z");
+    compare("xab", "(^a|b$)");
+    compare("ab", "(a$|b)+");
+}
+
+#[test]
+fn inline_flags() {
+    compare("ABC", "(?i)a(?-i:b)|c");
+    compare("xAbC", "(?i:a)b|(?x) C # comment");
+    compare("a\nb\nc", "(?m)^(b|c)$");
+    compare("ab\ncd", "(?s).+");
+    compare("ab\ncd", ".+$");
+    compare("ab\ncd", "(?sm)b.^c");
+}
+
+#[test]
+fn escape_sequences() {
+    compare("a\tb\tc", r"(\w)\t(\w)$");
+    compare("ABAB", r"(\x41\x42)+");
+    compare("café crème", r"[\u{e0}-\u{ff}]\s?");
+    compare("a\r\nb", r"\r?\n");
+    compare("1+2=3", r"\d\+\d\=");
+}
+
+#[test]
+fn alteration_backtracking() {
+    compare("abc", "(a|ab)c");
+    compare("abd", "(ab|a)bd");
+    compare("cat dog", "(cat|dog) (cat|dog)");
+    compare("ac", "a(b)?c");
+    compare("ababc", "(ab)+c");
+    compare("ababab", "(ab){2}");
+    compare("xyz", "(a|b|)x");
+    compare("abcabd", "(?:a(b)(?:c|d))+");
+    compare("ann@example", r"(?<user>\w+)@(?P<host>\w+)");
+}
+
+/// Tiny deterministic generator, keeps the suite reproducible without extra dependencies
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % bound as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.next(items.len())]
+    }
+}
+
+#[test]
+fn generated_patterns() {
+    let atoms = [
+        "a",
+        "b",
+        "c",
+        ".",
+        "[ab]",
+        "[^a]",
+        r"\d",
+        r"\w",
+        "(a|b)",
+        "(ab|a)",
+        "(a|bc|)",
+        "(c)",
+        "(?:a|bc)",
+        "((ab)+c)",
+        "((a|b){1,2}c?)",
+        "([^a]*?)",
+        "( |b*?)",
+        "[a-c]",
+        "[^b-c1]",
+        r"[\d ]",
+        "[]a]",
+        "[^]a-b]",
+        "é",
+        "[é-ж]",
+        "[^é]",
+    ];
+    let quantifiers = [
+        "", "", "", "?", "*", "+", "{2}", "{1,3}", "{0,2}", "{2,}", "??", "*?", "+?", "{1,3}?",
+        "{2,}?",
+    ];
+    let assertions = [r"\b", r"\B", r"\<", r"\>", "^", "$"];
+    let alphabet = ["a", "b", "c", "1", " ", "é", "ж", "_"];
+
+    let mut rng = Lcg(7);
+    for _ in 0..2000 {
+        let mut pattern = String::new();
+        if rng.next(6) == 0 {
+            pattern.push('^');
+        }
+        for _ in 0..1 + rng.next(4) {
+            if rng.next(5) == 0 {
+                pattern.push_str(rng.pick(&assertions));
+            }
+            pattern.push_str(rng.pick(&atoms));
+            pattern.push_str(rng.pick(&quantifiers));
+        }
+        if rng.next(6) == 0 {
+            pattern.push('$');
+        }
+
+        let input = (0..rng.next(8))
+            .map(|_| rng.pick(&alphabet))
+            .collect::<String>();
+        compare(&input, &pattern);
+    }
+}
+
+#[test]
+fn dfa_cache_reset() {
+    // Unanchored search tracks which of the last 13 characters are `a`, which needs 2^13
+    // DFA states, more than the lazy DFA keeps cached
+    let mut rng = Lcg(11);
+    for _ in 0..2 {
+        let input = (0..20000)
+            .map(|_| rng.pick(&["a", "b"]))
+            .collect::<String>();
+        compare(&input, "a[ab]{12}c");
+        compare(&format!("{input}c"), "a[ab]{12}c");
+    }
+}